@@ -0,0 +1,101 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// decoding of the aes-256-cbc formats produced by encrypt_data and encrypt_msg of earlier versions
+// Those formats are not authenticated, so they are only tried after decrypting using the current format failed, and
+// everything decrypted from them is flagged with the LEGACY_FORMAT warning. Legacy data is only accepted by
+// decrypt_data_compat and reencrypt_legacy, so stored data should be upgraded using reencrypt_legacy. Legacy messages
+// are only accepted by decrypt_msg_compat. Their signatures only cover the message, so anyone who received a signed
+// legacy message could forward it to someone else. They are therefore rejected if a public key for verification is given.
+//
+// legacy data: iv || aes-256-cbc ciphertext
+// legacy messages: kyber ciphertext || iv || aes-256-cbc ciphertext of "hex signature" + "." + "message"
+
+use hex::decode;
+use crate::error::DawnCryptoError;
+use crate::kdf;
+use crate::kem::{self, KemAlgorithm};
+use crate::secret::SecretBytes;
+use crate::symm::{self, SymmetricCipher};
+use crate::warning::*;
+
+const KYBER_CIPHERTEXT_LEN: usize = 1568;
+
+pub fn decrypt_data(encrypted_data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<(Vec<u8>, u8), DawnCryptoError> {
+	let err = match symm::decrypt_aead(encrypted_data, key, &[], cipher) {
+		Ok(data) => return Ok((data, NONE)),
+		Err(err) => DawnCryptoError::SymmetricDecryption(err)
+	};
+	if !symm::is_cbc_legacy(encrypted_data) { return Err(err) }
+	let data = symm::decrypt_cbc_legacy(encrypted_data, key).map_err(DawnCryptoError::SymmetricDecryption)?;
+	Ok((data, LEGACY_FORMAT))
+}
+
+// re-encrypt legacy data using the current format, data already using the current format is returned unchanged
+pub fn reencrypt(encrypted_data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	let (data, warning) = decrypt_data(encrypted_data, key, cipher)?;
+	let data = SecretBytes::from(data);
+	match warning & LEGACY_FORMAT {
+		0 => Ok(encrypted_data.to_vec()),
+		_ => crate::encrypt_data(&data, key, cipher)
+	}
+}
+
+// whether a message, which isn't a valid envelope, might be a legacy message for the given secret key
+// legacy messages were only encrypted using Kyber1024 keys
+pub fn is_legacy_msg(sec_key: &[u8], enc_msg: &[u8]) -> bool {
+	matches!(kem::decode_secret_key(sec_key), Ok((KemAlgorithm::Kyber1024, _)))
+		&& enc_msg.len() > KYBER_CIPHERTEXT_LEN
+		&& symm::is_cbc_legacy(&enc_msg[KYBER_CIPHERTEXT_LEN..])
+}
+
+// the errors are detailed, decrypt_msg makes them opaque
+pub fn decrypt_msg_bytes(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(Vec<u8>, SecretBytes, u8), DawnCryptoError> {
+	if pfs_key.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: pfs_key.len() }) }
+	if salt.len() != 32 { return Err(DawnCryptoError::InvalidSalt) }
+	let (algorithm, raw_sec_key) = kem::decode_secret_key(sec_key)?;
+	let (kyber_ciphertext, symm_enc_msg) = enc_msg.split_at(KYBER_CIPHERTEXT_LEN);
+	let kyber_shared_secret = kem::decrypt_shared_secret(algorithm, kyber_ciphertext, raw_sec_key).map_err(DawnCryptoError::KemDecapsulation)?;
+	let new_pfs_key = kdf::get_pfs_key_legacy(pfs_key, salt);
+	let secret = kdf::message_key_legacy(&kyber_shared_secret, &new_pfs_key);
+	let dec_msg = symm::decrypt_cbc_legacy(symm_enc_msg, &secret).map_err(DawnCryptoError::SymmetricDecryption)?;
+	let (signature, message) = decode_payload(&dec_msg)?;
+	
+	// legacy signatures only cover the message itself, so they can't prove who sent the message to us
+	let mut warning = LEGACY_FORMAT;
+	if signature.is_empty() {
+		warning += NO_SIGNATURE;
+	}
+	else if pub_key.is_some() {
+		return Err(DawnCryptoError::SignatureInvalid("legacy signatures are not bound to the recipient".into()))
+	}
+	Ok((message, new_pfs_key, warning))
+}
+
+// split the payload of a legacy message, "hex signature" + "." + "message"
+fn decode_payload(payload: &[u8]) -> Result<(Vec<u8>, Vec<u8>), DawnCryptoError> {
+	let separator = match payload.iter().position(|byte| *byte == b'.') {
+		Some(res) => res,
+		None => return Err(DawnCryptoError::SignatureMissing)
+	};
+	let signature = match decode(&payload[..separator]) {
+		Ok(res) => res,
+		Err(_) => return Err(DawnCryptoError::InvalidMessage("signature parsing failed"))
+	};
+	Ok((signature, payload[separator + 1..].to_vec()))
+}
@@ -0,0 +1,188 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// Encrypted container format allowing random access (e.g. seeking in videos stored on a content server)
+// All chunks have the same size except for the last one, so the position of every chunk can be calculated from the header.
+// The header contains the length of the plaintext and gets authenticated with every chunk, so only the chunks covering a
+// requested range need to be fetched and authenticated.
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use rand::Rng;
+use crate::stream::{derive_chunk_key, chunk_nonce, read_full, DEFAULT_CHUNK_SIZE, MAX_CHUNK_SIZE};
+use crate::secret::SecretBytes;
+use crate::symm::{self, SymmetricCipher};
+
+const CONTAINER_VERSION: u8 = 1;
+
+// version || cipher id || chunk size || plaintext length || salt
+pub const CONTAINER_HEADER_LEN: usize = 1 + 1 + 4 + 8 + 32;
+
+fn invalid_data(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn chunk_count(plaintext_len: u64, chunk_size: usize) -> u64 {
+	// an empty container still consists of one (empty) chunk
+	plaintext_len.div_ceil(chunk_size as u64).max(1)
+}
+
+// encrypt exactly len bytes from the reader into a container written to the writer, using the default chunk size
+pub fn encrypt_container<R: Read, W: Write>(reader: R, len: u64, writer: W, key: &[u8], cipher: SymmetricCipher) -> io::Result<()> {
+	encrypt_container_with_chunk_size(reader, len, writer, key, cipher, DEFAULT_CHUNK_SIZE)
+}
+
+pub fn encrypt_container_with_chunk_size<R: Read, W: Write>(mut reader: R, len: u64, mut writer: W, key: &[u8], cipher: SymmetricCipher, chunk_size: usize) -> io::Result<()> {
+	if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk size invalid"))
+	}
+	let salt = rand::thread_rng().gen::<[u8; 32]>();
+	let key = derive_chunk_key(key, &salt, b"dawn container chunk key")?;
+	
+	let mut header = vec![CONTAINER_VERSION, cipher.id()];
+	header.extend_from_slice(&(chunk_size as u32).to_be_bytes());
+	header.extend_from_slice(&len.to_be_bytes());
+	header.extend_from_slice(&salt);
+	writer.write_all(&header)?;
+	
+	let chunks = chunk_count(len, chunk_size);
+	let mut remaining = len;
+	let mut buffer = vec![0u8; chunk_size];
+	for counter in 0..chunks {
+		let chunk_len = remaining.min(chunk_size as u64) as usize;
+		if read_full(&mut reader, &mut buffer[..chunk_len])? != chunk_len {
+			return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "reader provided less data than announced"))
+		}
+		remaining -= chunk_len as u64;
+		let nonce = chunk_nonce(cipher, counter, counter == chunks - 1)?;
+		let chunk = match symm::seal(&buffer[..chunk_len], &key, &nonce, &header, cipher) {
+			Ok(res) => res,
+			Err(_) => return Err(io::Error::other("chunk encryption failed"))
+		};
+		writer.write_all(&chunk)?;
+	}
+	if read_full(&mut reader, &mut [0u8])? != 0 {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "reader provided more data than announced"))
+	}
+	writer.flush()
+}
+
+// decrypts arbitrary ranges of a container, fetching only the chunks that are needed
+// fetch(offset, len) needs to return exactly len bytes of the container starting at offset, e.g. using an HTTP range request
+pub struct ContainerReader<F: FnMut(u64, usize) -> io::Result<Vec<u8>>> {
+	fetch: F,
+	key: SecretBytes,
+	cipher: SymmetricCipher,
+	header: Vec<u8>,
+	chunk_size: usize,
+	plaintext_len: u64,
+}
+
+impl<F: FnMut(u64, usize) -> io::Result<Vec<u8>>> ContainerReader<F> {
+	// fetch the header of the container. It only gets authenticated together with the first chunk that is read.
+	pub fn open(key: &[u8], mut fetch: F) -> io::Result<ContainerReader<F>> {
+		let header = fetch(0, CONTAINER_HEADER_LEN)?;
+		if header.len() != CONTAINER_HEADER_LEN {
+			return Err(invalid_data("container header incomplete"))
+		}
+		if header[0] != CONTAINER_VERSION {
+			return Err(invalid_data("unsupported container version"))
+		}
+		let cipher = match SymmetricCipher::from_id(header[1]) {
+			Some(res) => res,
+			None => return Err(invalid_data("unknown cipher"))
+		};
+		let chunk_size = u32::from_be_bytes(header[2..6].try_into().unwrap()) as usize;
+		if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+			return Err(invalid_data("chunk size invalid"))
+		}
+		let plaintext_len = u64::from_be_bytes(header[6..14].try_into().unwrap());
+		let key = derive_chunk_key(key, &header[14..], b"dawn container chunk key")?;
+		
+		Ok(ContainerReader {
+			fetch,
+			key,
+			cipher,
+			header,
+			chunk_size,
+			plaintext_len,
+		})
+	}
+	
+	// length of the decrypted content
+	pub fn len(&self) -> u64 {
+		self.plaintext_len
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.plaintext_len == 0
+	}
+	
+	// decrypt the given byte range of the content
+	pub fn read_range(&mut self, range: Range<u64>) -> io::Result<Vec<u8>> {
+		if range.start > range.end || range.end > self.plaintext_len {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "range out of bounds"))
+		}
+		if range.is_empty() { return Ok(vec![]) }
+		
+		let chunk_size = self.chunk_size as u64;
+		let tag_len = self.cipher.tag_len() as u64;
+		let chunks = chunk_count(self.plaintext_len, self.chunk_size);
+		let first_chunk = range.start / chunk_size;
+		let last_chunk = (range.end - 1) / chunk_size;
+		
+		// fetch all needed chunks at once
+		// the header isn't authenticated yet, so a manipulated plaintext length must not make the calculation overflow
+		let offset = first_chunk.checked_mul(chunk_size + tag_len).and_then(|offset| offset.checked_add(CONTAINER_HEADER_LEN as u64));
+		let end = (last_chunk + 1).checked_mul(chunk_size).map(|end| end.min(self.plaintext_len));
+		let tags_len = (last_chunk - first_chunk + 1).checked_mul(tag_len);
+		let (offset, fetch_len) = match (offset, end, tags_len) {
+			(Some(offset), Some(end), Some(tags_len)) => match (end - first_chunk * chunk_size).checked_add(tags_len) {
+				Some(fetch_len) => (offset, fetch_len),
+				None => return Err(invalid_data("container too large"))
+			},
+			_ => return Err(invalid_data("container too large"))
+		};
+		let fetch_len = match usize::try_from(fetch_len) {
+			Ok(res) => res,
+			Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "range too large"))
+		};
+		let ciphertext = (self.fetch)(offset, fetch_len)?;
+		if ciphertext.len() != fetch_len {
+			return Err(invalid_data("fetched ciphertext has wrong length"))
+		}
+		
+		let mut plaintext = Vec::with_capacity((range.end - range.start) as usize);
+		for (i, chunk) in ciphertext.chunks((chunk_size + tag_len) as usize).enumerate() {
+			let counter = first_chunk + i as u64;
+			let nonce = chunk_nonce(self.cipher, counter, counter == chunks - 1)?;
+			let mut dec_chunk = match symm::open(chunk, &self.key, &nonce, &self.header, self.cipher) {
+				Ok(res) => res,
+				Err(_) => return Err(invalid_data("chunk authentication failed"))
+			};
+			
+			// cut the chunk to the requested range
+			let chunk_start = counter * chunk_size;
+			let from = range.start.saturating_sub(chunk_start) as usize;
+			let to = (range.end - chunk_start).min(dec_chunk.len() as u64) as usize;
+			dec_chunk.truncate(to);
+			plaintext.extend_from_slice(&dec_chunk[from..]);
+		}
+		Ok(plaintext)
+	}
+}
@@ -0,0 +1,73 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// convergent encryption for deduplicating media on content servers
+// The key is derived from the hash of the content and a convergence secret of a user or group, so everyone holding the
+// secret encrypts the same content to the same ciphertext and the server only needs to store it once. Only holders of the
+// secret can confirm a guess of the content, while the server learns nothing but which uploads are identical.
+// The encryption is deterministic in the style of SIV: the nonce is derived from the key, the associated data and the
+// plaintext, and gets checked again after decryption. The cipher itself uses a key derived separately from the nonces.
+//
+// format: synthetic nonce || ciphertext || tag, like the output of encrypt_data
+
+use crate::error::DawnCryptoError;
+use crate::hash;
+use crate::kdf;
+use crate::secret::SecretBytes;
+use crate::symm::{self, SymmetricCipher};
+
+// encrypt data deterministically, the same key, associated data and data always result in the same ciphertext
+pub fn seal_deterministic(data: &[u8], key: &[u8], aad: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	if key.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: key.len() }) }
+	let nonce = kdf::siv_nonce(key, aad, data, cipher.nonce_len())?;
+	let mut enc_data = symm::seal(data, &kdf::siv_key(key)?, &nonce, aad, cipher).map_err(DawnCryptoError::SymmetricEncryption)?;
+	let mut ciphertext = nonce.to_vec();
+	ciphertext.append(&mut enc_data);
+	Ok(ciphertext)
+}
+
+// decrypt data encrypted using seal_deterministic, rejecting it if the nonce doesn't match the decrypted data
+pub fn open_deterministic(ciphertext: &[u8], key: &[u8], aad: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	if key.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: key.len() }) }
+	if ciphertext.len() < cipher.nonce_len() + cipher.tag_len() {
+		return Err(DawnCryptoError::SymmetricDecryption("ciphertext too short".into()))
+	}
+	let (nonce, enc_data) = ciphertext.split_at(cipher.nonce_len());
+	let data = symm::open(enc_data, &kdf::siv_key(key)?, nonce, aad, cipher).map_err(DawnCryptoError::SymmetricDecryption)?;
+	if kdf::siv_nonce(key, aad, &data, cipher.nonce_len())? != SecretBytes::from_slice(nonce) {
+		return Err(DawnCryptoError::SymmetricDecryption("synthetic nonce mismatch".into()))
+	}
+	Ok(data)
+}
+
+// the key the content gets encrypted with for holders of the convergence secret
+pub fn key(data: &[u8], convergence_secret: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	if convergence_secret.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: convergence_secret.len() }) }
+	kdf::convergent_key(&hash::hash(data), convergence_secret)
+}
+
+// returns the ciphertext and the key, which needs to be sent to the recipients (e.g. inside a message)
+pub fn encrypt(data: &[u8], convergence_secret: &[u8], cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	let key = key(data, convergence_secret)?;
+	let ciphertext = seal_deterministic(data, &key, &[], cipher)?;
+	Ok((ciphertext, key))
+}
+
+pub fn decrypt(ciphertext: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	open_deterministic(ciphertext, key, &[], cipher)
+}
@@ -0,0 +1,67 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// versions of the message decryption functions reporting why a message was rejected
+// The functions in the crate root report every failure caused by the received message as DecryptionFailed, so the
+// behaviour of a client can't reveal whether e.g. the KEM ciphertext, the encryption or the signature was invalid.
+// Only use these functions for debugging and never let their errors influence what a client sends back.
+
+use crate::*;
+
+pub fn decrypt_msg(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(String, SecretBytes, u8), DawnCryptoError> {
+	let (message, new_pfs_key, warning) = decrypt_msg_bytes(sec_key, pub_key, pfs_key, salt, enc_msg)?;
+	match String::from_utf8(message) {
+		Ok(message) => Ok((message, new_pfs_key, warning)),
+		Err(_) => Err(DawnCryptoError::InvalidMessage("message is not valid UTF-8"))
+	}
+}
+
+pub fn decrypt_msg_bytes(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(Vec<u8>, SecretBytes, u8), DawnCryptoError> {
+	crate::decrypt_message(false, sec_key, pub_key, pfs_key, salt, enc_msg)
+}
+
+pub fn decrypt_msg_compat(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(String, SecretBytes, u8), DawnCryptoError> {
+	let (message, new_pfs_key, warning) = match MessageEnvelope::parse(enc_msg).is_err() && compat::is_legacy_msg(sec_key, enc_msg) {
+		true => compat::decrypt_msg_bytes(sec_key, pub_key, pfs_key, salt, enc_msg)?,
+		false => decrypt_msg_bytes(sec_key, pub_key, pfs_key, salt, enc_msg)?
+	};
+	match String::from_utf8(message) {
+		Ok(message) => Ok((message, new_pfs_key, warning)),
+		Err(_) => Err(DawnCryptoError::InvalidMessage("message is not valid UTF-8"))
+	}
+}
+
+pub fn decrypt_msg_hybrid(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(String, SecretBytes, u8), DawnCryptoError> {
+	let (message, new_pfs_key, warning) = decrypt_msg_bytes_hybrid(sec_key, pub_key, pfs_key, salt, enc_msg)?;
+	match String::from_utf8(message) {
+		Ok(message) => Ok((message, new_pfs_key, warning)),
+		Err(_) => Err(DawnCryptoError::InvalidMessage("message is not valid UTF-8"))
+	}
+}
+
+pub fn decrypt_msg_bytes_hybrid(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(Vec<u8>, SecretBytes, u8), DawnCryptoError> {
+	crate::decrypt_message(true, sec_key, pub_key, pfs_key, salt, enc_msg)
+}
+
+pub fn decrypt_ratchet(ratchet: &mut Ratchet, enc_msg: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+	ratchet.decrypt_detailed(enc_msg)
+}
+
+pub fn decrypt_session(session: &mut Session, enc_msg: &[u8]) -> Result<(Vec<u8>, u8), DawnCryptoError> {
+	session.decrypt_detailed(enc_msg)
+}
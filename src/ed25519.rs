@@ -0,0 +1,49 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// classical signatures, only used as part of composite signatures
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::Rng;
+use zeroize::Zeroize;
+use crate::secret::SecretBytes;
+
+pub fn keygen() -> (Vec<u8>, SecretBytes) {
+	let mut secret_key = rand::thread_rng().gen::<[u8; 32]>();
+	let public_key = SigningKey::from_bytes(&secret_key).verifying_key().to_bytes();
+	let secret = SecretBytes::from_slice(&secret_key);
+	secret_key.zeroize();
+	(public_key.to_vec(), secret)
+}
+
+pub fn sign(sec_key: &[u8], msg: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	let key = SigningKey::from_bytes(sec_key.try_into()?);
+	Ok(key.try_sign(msg)?.to_bytes().to_vec())
+}
+
+pub fn verify(signature: &[u8], pub_key: &[u8], msg: &[u8]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	let key = VerifyingKey::from_bytes(pub_key.try_into()?)?;
+	let signature = Signature::from_slice(signature)?;
+	key.verify_strict(msg, &signature)?;
+	Ok(())
+}
+
+// lengths of public key, secret key and signature
+pub fn lengths() -> (usize, usize, usize) {
+	(32, 32, 64)
+}
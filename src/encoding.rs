@@ -0,0 +1,69 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// helpers for the binary formats of this crate
+// variable-length fields are prefixed with their length as 32-bit big endian integer
+
+pub fn push_field(output: &mut Vec<u8>, field: &[u8]) {
+	output.extend_from_slice(&(field.len() as u32).to_be_bytes());
+	output.extend_from_slice(field);
+}
+
+// reads values from a byte slice, returning None if there are not enough bytes left
+pub struct Reader<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Reader<'a> {
+	pub fn new(data: &'a [u8]) -> Reader<'a> {
+		Reader { data, position: 0 }
+	}
+	
+	pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+		if self.data.len() - self.position < len { return None }
+		let bytes = &self.data[self.position..self.position + len];
+		self.position += len;
+		Some(bytes)
+	}
+	
+	pub fn read_u8(&mut self) -> Option<u8> {
+		Some(self.read_bytes(1)?[0])
+	}
+	
+	pub fn read_u32(&mut self) -> Option<u32> {
+		Some(u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap()))
+	}
+	
+	pub fn read_u64(&mut self) -> Option<u64> {
+		Some(u64::from_be_bytes(self.read_bytes(8)?.try_into().unwrap()))
+	}
+	
+	pub fn read_field(&mut self) -> Option<&'a [u8]> {
+		let len = self.read_u32()? as usize;
+		self.read_bytes(len)
+	}
+	
+	pub fn remaining(&self) -> usize {
+		self.data.len() - self.position
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.position == self.data.len()
+	}
+}
@@ -0,0 +1,143 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use crate::encoding::{push_field, Reader};
+use crate::error::DawnCryptoError;
+use crate::hash;
+use crate::kem;
+use crate::symm::SymmetricCipher;
+
+// versioned binary format of encrypted messages:
+// magic byte || format version || algorithm suite || kem ciphertext (length-prefixed) || encrypted payload (length-prefixed)
+// everything in front of the encrypted payload is authenticated as associated data
+// the decrypted payload consists of the signature and the message, both length-prefixed, followed by the padding
+pub const MAGIC: u8 = 0xda;
+pub const VERSION: u8 = 1;
+
+// key encapsulation mechanisms, stored in the upper half of the suite byte (the lower half is the symmetric cipher)
+pub const KEM_KYBER1024: u8 = 1;
+pub const KEM_X25519_KYBER1024: u8 = 2;
+pub const KEM_MLKEM768: u8 = 3;
+pub const KEM_MLKEM1024: u8 = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageEnvelope {
+	pub version: u8,
+	pub kem: u8,
+	pub cipher: SymmetricCipher,
+	pub kem_ciphertext: Vec<u8>,
+	pub payload: Vec<u8>,
+}
+
+impl MessageEnvelope {
+	pub fn suite(&self) -> u8 {
+		self.kem << 4 | self.cipher.id()
+	}
+	
+	// the part of the encoded envelope that gets authenticated when encrypting the payload
+	pub fn associated_data(&self) -> Vec<u8> {
+		let mut output = vec![MAGIC, self.version, self.suite()];
+		push_field(&mut output, &self.kem_ciphertext);
+		output
+	}
+	
+	pub fn encode(&self) -> Vec<u8> {
+		let mut output = self.associated_data();
+		push_field(&mut output, &self.payload);
+		output
+	}
+	
+	// parse an encoded envelope, rejecting unknown versions and algorithms
+	pub fn parse(bytes: &[u8]) -> Result<MessageEnvelope, DawnCryptoError> {
+		let mut reader = Reader::new(bytes);
+		if reader.read_u8() != Some(MAGIC) {
+			return Err(DawnCryptoError::InvalidMessage("not a message envelope"))
+		}
+		let version = match reader.read_u8() {
+			Some(VERSION) => VERSION,
+			Some(version) => return Err(DawnCryptoError::UnsupportedVersion(version)),
+			None => return Err(DawnCryptoError::InvalidMessage("message too short"))
+		};
+		let suite = match reader.read_u8() {
+			Some(suite) => suite,
+			None => return Err(DawnCryptoError::InvalidMessage("message too short"))
+		};
+		let kem = suite >> 4;
+		if !matches!(kem, KEM_KYBER1024 | KEM_X25519_KYBER1024 | KEM_MLKEM768 | KEM_MLKEM1024) {
+			return Err(DawnCryptoError::UnsupportedAlgorithm(suite))
+		}
+		let cipher = match SymmetricCipher::from_id(suite & 0x0f) {
+			Some(cipher) => cipher,
+			None => return Err(DawnCryptoError::UnsupportedAlgorithm(suite))
+		};
+		let (kem_ciphertext, payload) = match (reader.read_field(), reader.read_field()) {
+			(Some(kem_ciphertext), Some(payload)) => (kem_ciphertext.to_vec(), payload.to_vec()),
+			_ => return Err(DawnCryptoError::InvalidMessage("message truncated"))
+		};
+		if !reader.is_empty() {
+			return Err(DawnCryptoError::InvalidMessage("trailing data after message"))
+		}
+		Ok(MessageEnvelope {
+			version,
+			kem,
+			cipher,
+			kem_ciphertext,
+			payload,
+		})
+	}
+}
+
+// encode the plaintext payload: signature (empty if unsigned) and message, both length-prefixed
+pub fn encode_payload(signature: &[u8], msg: &[u8]) -> Vec<u8> {
+	let mut output = Vec::with_capacity(8 + signature.len() + msg.len());
+	push_field(&mut output, signature);
+	push_field(&mut output, msg);
+	output
+}
+
+// split the decrypted payload of a message into signature and message
+pub fn decode_payload(payload: &[u8]) -> Result<(Vec<u8>, Vec<u8>), DawnCryptoError> {
+	let mut reader = Reader::new(payload);
+	let (signature, msg) = match (reader.read_field(), reader.read_field()) {
+		(Some(signature), Some(msg)) => (signature.to_vec(), msg.to_vec()),
+		_ => return Err(DawnCryptoError::InvalidMessage("payload truncated"))
+	};
+	if !reader.is_empty() {
+		return Err(DawnCryptoError::InvalidMessage("trailing data after payload"))
+	}
+	Ok((signature, msg))
+}
+
+// the data that gets signed for a message: besides the message itself, the signature covers the recipient's public key,
+// the kem ciphertext and the PFS epoch, so a signed message can't be forwarded or re-encrypted to someone else
+// the public key is covered as kem id || raw key, so the tagged and untagged encodings of a Kyber1024 key are the same
+pub fn signed_data(kem: u8, recipient_pub_key: &[u8], kem_ciphertext: &[u8], pfs_key: &[u8], msg: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+	let raw_key = match kem {
+		KEM_X25519_KYBER1024 => recipient_pub_key,
+		_ => kem::decode_public_key(recipient_pub_key)?.1
+	};
+	let mut epoch_input = b"dawn-crypto pfs epoch".to_vec();
+	epoch_input.extend_from_slice(pfs_key);
+	let mut output = vec![];
+	push_field(&mut output, b"dawn-crypto signed message v1");
+	push_field(&mut output, &[&[kem], raw_key].concat());
+	push_field(&mut output, kem_ciphertext);
+	push_field(&mut output, &hash::hash(&epoch_input));
+	push_field(&mut output, msg);
+	Ok(output)
+}
@@ -0,0 +1,92 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::error::Error;
+use std::fmt;
+
+// the error type returned by all public functions of this crate
+// errors from the underlying cryptographic libraries are kept as source where available
+#[derive(Debug)]
+pub enum DawnCryptoError {
+	InvalidKeyLength { expected: usize, actual: usize },
+	InvalidKey(Box<dyn Error + Send + Sync>),
+	InvalidInput(&'static str),
+	KemEncapsulation(Box<dyn Error + Send + Sync>),
+	KemDecapsulation(Box<dyn Error + Send + Sync>),
+	KeyAgreement(Box<dyn Error + Send + Sync>),
+	KeyDerivation(Box<dyn Error + Send + Sync>),
+	SymmetricEncryption(Box<dyn Error + Send + Sync>),
+	SymmetricDecryption(Box<dyn Error + Send + Sync>),
+	DecryptionFailed,
+	Signing(Box<dyn Error + Send + Sync>),
+	SignatureInvalid(Box<dyn Error + Send + Sync>),
+	SignatureMissing,
+	InvalidMessage(&'static str),
+	Replay(u64),
+	UnsupportedVersion(u8),
+	UnsupportedAlgorithm(u8),
+	InvalidId,
+	InvalidSalt,
+	InvalidParameters,
+	InvalidTimestamp(&'static str),
+}
+
+impl fmt::Display for DawnCryptoError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DawnCryptoError::InvalidKeyLength { expected, actual } => write!(f, "invalid key length. Expected {} bytes, got {} bytes", expected, actual),
+			DawnCryptoError::InvalidKey(_) => write!(f, "invalid key"),
+			DawnCryptoError::InvalidInput(reason) => write!(f, "invalid input: {}", reason),
+			DawnCryptoError::KemEncapsulation(_) => write!(f, "failed to get kyber shared secret"),
+			DawnCryptoError::KemDecapsulation(_) => write!(f, "could not decrypt kyber secret"),
+			DawnCryptoError::KeyAgreement(_) => write!(f, "failed to derive curve secret"),
+			DawnCryptoError::KeyDerivation(_) => write!(f, "key derivation failed"),
+			DawnCryptoError::SymmetricEncryption(_) => write!(f, "symmetric encryption failed"),
+			DawnCryptoError::SymmetricDecryption(_) => write!(f, "symmetric decryption failed"),
+			DawnCryptoError::DecryptionFailed => write!(f, "decryption failed"),
+			DawnCryptoError::Signing(_) => write!(f, "failed to sign message"),
+			DawnCryptoError::SignatureInvalid(_) => write!(f, "signature verification failed"),
+			DawnCryptoError::SignatureMissing => write!(f, "signature not found"),
+			DawnCryptoError::InvalidMessage(reason) => write!(f, "invalid message: {}", reason),
+			DawnCryptoError::Replay(sequence) => write!(f, "message {} was already received", sequence),
+			DawnCryptoError::UnsupportedVersion(version) => write!(f, "unsupported format version {}", version),
+			DawnCryptoError::UnsupportedAlgorithm(id) => write!(f, "unsupported algorithm {:#04x}", id),
+			DawnCryptoError::InvalidId => write!(f, "invalid id"),
+			DawnCryptoError::InvalidSalt => write!(f, "invalid salt"),
+			DawnCryptoError::InvalidParameters => write!(f, "parameters exceed the supported limits"),
+			DawnCryptoError::InvalidTimestamp(reason) => write!(f, "invalid timestamp: {}", reason),
+		}
+	}
+}
+
+impl Error for DawnCryptoError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			DawnCryptoError::InvalidKey(err)
+			| DawnCryptoError::KemEncapsulation(err)
+			| DawnCryptoError::KemDecapsulation(err)
+			| DawnCryptoError::KeyAgreement(err)
+			| DawnCryptoError::KeyDerivation(err)
+			| DawnCryptoError::SymmetricEncryption(err)
+			| DawnCryptoError::SymmetricDecryption(err)
+			| DawnCryptoError::Signing(err)
+			| DawnCryptoError::SignatureInvalid(err) => Some(err.as_ref()),
+			_ => None
+		}
+	}
+}
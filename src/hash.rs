@@ -16,14 +16,104 @@
 	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
 */
 
+use argon2::{Algorithm, Argon2, Params, Version};
 use openssl::sha::sha256 as hash_function;
+use openssl::md::Md;
+use openssl::pkey::Id;
+use openssl::pkey_ctx::PkeyCtx;
+use zeroize::Zeroize;
+use crate::encoding::Reader;
+use crate::secret::SecretBytes;
 
 pub fn hash(input: &[u8]) -> Vec<u8> {
 	hash_function(input).to_vec()
 }
 
-pub fn get_pfs_key(key: &[u8], salt: &[u8]) -> Vec<u8> {
-	let mut hash_input = key.to_vec();
-	hash_input.append(&mut salt.to_vec());
-	hash_function(&hash_input).to_vec()
+// hash secret input, the result is treated as secret as well
+pub fn hash_secret(input: &[u8]) -> SecretBytes {
+	let mut output = hash_function(input);
+	let secret = SecretBytes::from_slice(&output);
+	output.zeroize();
+	secret
+}
+
+// derive output keying material of the given length using HKDF-SHA256 (RFC 5869)
+pub fn hkdf(ikm: &[u8], salt: &[u8], info: &[u8], len: usize) -> Result<SecretBytes, Box<dyn std::error::Error + Send + Sync>> {
+	let mut ctx = PkeyCtx::new_id(Id::HKDF)?;
+	ctx.derive_init()?;
+	ctx.set_hkdf_md(Md::sha256())?;
+	ctx.set_hkdf_key(ikm)?;
+	ctx.set_hkdf_salt(salt)?;
+	ctx.add_hkdf_info(info)?;
+	let mut output = SecretBytes::from(vec![0u8; len]);
+	ctx.derive(Some(&mut output))?;
+	Ok(output)
+}
+
+pub const PASSWORD_SALT_LEN: usize = 16;
+
+// parameters of Argon2id: memory in KiB, number of iterations and degree of parallelism
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Argon2Params {
+	pub memory_kib: u32,
+	pub iterations: u32,
+	pub parallelism: u32,
+}
+
+// 64 MiB, 3 iterations and 4 lanes, as recommended by RFC 9106 for memory-constrained environments
+impl Default for Argon2Params {
+	fn default() -> Argon2Params {
+		Argon2Params {
+			memory_kib: 64 * 1024,
+			iterations: 3,
+			parallelism: 4,
+		}
+	}
+}
+
+impl Argon2Params {
+	// the strongest parameters we accept (256 MiB, 8 iterations, 8 lanes)
+	// parameters read from a stored header are checked against this, so a manipulated file can't make us spend
+	// gigabytes of memory or minutes of cpu time before the header is authenticated
+	pub const MAX: Argon2Params = Argon2Params {
+		memory_kib: 256 * 1024,
+		iterations: 8,
+		parallelism: 8,
+	};
+	
+	pub fn within_limits(&self) -> bool {
+		self.memory_kib <= Argon2Params::MAX.memory_kib
+			&& self.iterations <= Argon2Params::MAX.iterations
+			&& self.parallelism <= Argon2Params::MAX.parallelism
+	}
+	
+	// encoded as memory || iterations || parallelism (each u32) || salt
+	pub fn encode_with_salt(&self, salt: &[u8]) -> Vec<u8> {
+		let mut output = Vec::with_capacity(12 + salt.len());
+		output.extend_from_slice(&self.memory_kib.to_be_bytes());
+		output.extend_from_slice(&self.iterations.to_be_bytes());
+		output.extend_from_slice(&self.parallelism.to_be_bytes());
+		output.extend_from_slice(salt);
+		output
+	}
+	
+	pub fn decode_with_salt(encoded: &[u8]) -> Option<(Argon2Params, &[u8])> {
+		let mut reader = Reader::new(encoded);
+		let params = Argon2Params {
+			memory_kib: reader.read_u32()?,
+			iterations: reader.read_u32()?,
+			parallelism: reader.read_u32()?,
+		};
+		let salt = reader.read_bytes(PASSWORD_SALT_LEN)?;
+		if !reader.is_empty() { return None }
+		Some((params, salt))
+	}
+}
+
+// derive a 32-byte key from a password using Argon2id
+pub fn argon2id(password: &[u8], salt: &[u8], params: &Argon2Params) -> Result<SecretBytes, Box<dyn std::error::Error + Send + Sync>> {
+	let params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(32))?;
+	let mut output = SecretBytes::from(vec![0u8; 32]);
+	Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(password, salt, &mut output)?;
+	Ok(output)
 }
@@ -0,0 +1,84 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// hybrid key encapsulation combining X25519 and Kyber1024
+// the shared secrets of both get combined with a hash function in the style of X-Wing, so the result stays secret
+// as long as one of them isn't broken. The combiner includes the X25519 ciphertext and public key, because
+// X25519 on its own isn't a secure KEM, while Kyber's shared secret already depends on its ciphertext.
+//
+// public key: X25519 public key (32 bytes) || Kyber public key
+// secret key: X25519 secret key (32 bytes) || Kyber secret key
+// ciphertext: ephemeral X25519 public key (32 bytes) || Kyber ciphertext
+
+use openssl::hash::{hash, MessageDigest};
+use crate::kyber;
+use crate::secret::SecretBytes;
+use crate::x25519;
+
+const LABEL: &[u8] = b"dawn-crypto hybrid kem x25519 kyber1024";
+
+pub fn keygen() -> (Vec<u8>, SecretBytes) {
+	let (curve_pk, curve_sk) = x25519::keygen();
+	let (kyber_pk, kyber_sk) = kyber::keygen();
+	let mut public_key = curve_pk;
+	public_key.extend_from_slice(&kyber_pk);
+	(public_key, SecretBytes::concat(&[&curve_sk, &kyber_sk]))
+}
+
+// combine the shared secrets using SHA3-256
+pub fn combine(kyber_secret: &[u8], curve_secret: &[u8], curve_ciphertext: &[u8], curve_pub_key: &[u8]) -> Result<SecretBytes, Box<dyn std::error::Error + Send + Sync>> {
+	let input = SecretBytes::concat(&[kyber_secret, curve_secret, curve_ciphertext, curve_pub_key, LABEL]);
+	Ok(SecretBytes::from_slice(&hash(MessageDigest::sha3_256(), &input)?))
+}
+
+pub fn get_shared_secret(pub_key: &[u8]) -> Result<(SecretBytes, Vec<u8>), Box<dyn std::error::Error + Send + Sync>> {
+	if pub_key.len() < 32 { return Err("public key too short".into()) }
+	let (curve_pub_key, kyber_pub_key) = pub_key.split_at(32);
+	
+	let (kyber_secret, kyber_ciphertext) = kyber::get_shared_secret(kyber_pub_key)?;
+	
+	// X25519 with an ephemeral key, its public key is the ciphertext
+	let (curve_ciphertext, ephemeral_secret) = x25519::keygen();
+	let curve_secret = x25519::get_shared_secret(&ephemeral_secret, curve_pub_key)?;
+	
+	let shared_secret = combine(&kyber_secret, &curve_secret, &curve_ciphertext, curve_pub_key)?;
+	let mut ciphertext = curve_ciphertext;
+	ciphertext.extend_from_slice(&kyber_ciphertext);
+	Ok((shared_secret, ciphertext))
+}
+
+pub fn decrypt_shared_secret(ciphertext: &[u8], sec_key: &[u8]) -> Result<SecretBytes, Box<dyn std::error::Error + Send + Sync>> {
+	if ciphertext.len() < 32 { return Err("ciphertext too short".into()) }
+	if sec_key.len() < 32 { return Err("secret key too short".into()) }
+	let (curve_ciphertext, kyber_ciphertext) = ciphertext.split_at(32);
+	let (curve_sec_key, kyber_sec_key) = sec_key.split_at(32);
+	
+	let kyber_secret = kyber::decrypt_shared_secret(kyber_ciphertext, kyber_sec_key)?;
+	let curve_secret = x25519::get_shared_secret(curve_sec_key, curve_ciphertext)?;
+	let curve_pub_key = x25519::public_key_from_secret(curve_sec_key)?;
+	
+	combine(&kyber_secret, &curve_secret, curve_ciphertext, &curve_pub_key)
+}
+
+pub fn public_key_from_secret(sec_key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	if sec_key.len() < 32 { return Err("secret key too short".into()) }
+	let (curve_sec_key, kyber_sec_key) = sec_key.split_at(32);
+	let mut public_key = x25519::public_key_from_secret(curve_sec_key)?;
+	public_key.append(&mut kyber::public_key_from_secret(kyber_sec_key)?);
+	Ok(public_key)
+}
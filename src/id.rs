@@ -16,10 +16,13 @@
 	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
 */
 
-use hex::encode;
+use hex::{decode, encode};
 use rand::Rng;
 use regex::Regex;
 use crate::hash;
+use crate::kdf;
+use crate::error::DawnCryptoError;
+use crate::secret::SecretBytes;
 use chrono::{Duration, prelude::*};
 
 lazy_static! {
@@ -34,10 +37,14 @@ pub fn gen_id() -> String {
 	encode(id)
 }
 
+pub fn is_id_seed(id: &str) -> bool {
+	IS_ID_SEED.is_match(id)
+}
+
 // generate temporary id using seed and default modifier
-pub fn get_temp_id(id: &str) -> Result<String, String> {
+pub fn get_temp_id(id: &str) -> Result<String, DawnCryptoError> {
 	if !IS_ID_SEED.is_match(id) {
-		return Err("invalid id".to_string())
+		return Err(DawnCryptoError::InvalidId)
 	}
 	
 	// get current time
@@ -45,54 +52,64 @@ pub fn get_temp_id(id: &str) -> Result<String, String> {
 	let date_modifier = c_time.date_naive().format("%Y%m%d").to_string();
 	let time_modifier = c_time.time().format("%H").to_string().parse::<u8>();
 	if time_modifier.is_err() {
-		return Err("failed to format time".to_string());
+		return Err(DawnCryptoError::InvalidTimestamp("failed to format time"));
 	}
 	
 	// round to 4-hour resolution
 	let time_modifier = time_modifier.unwrap() / 4;
 	
 	let modifier = date_modifier + &time_modifier.to_string();
-	let input = String::from(id) + &modifier;
-	let hash = encode(hash::hash(input.as_bytes()));
+	let input = SecretBytes::concat(&[id.as_bytes(), modifier.as_bytes()]);
+	let hash = encode(hash::hash(&input));
 	Ok(hash)
 }
 
 // generate temporary id using seed and modifier (i.e. time)
-pub fn get_custom_temp_id(id: &str, modifier: &str) -> Result<String, String> {
+pub fn get_custom_temp_id(id: &str, modifier: &str) -> Result<String, DawnCryptoError> {
 	if !IS_ID_SEED.is_match(id) {
-		return Err("invalid id".to_string())
+		return Err(DawnCryptoError::InvalidId)
 	}
 	if modifier.is_empty() {
-		return Err("modifier was empty".to_string())
+		return Err(DawnCryptoError::InvalidTimestamp("modifier was empty"))
 	}
-	let input = String::from(id) + modifier;
-	let hash = encode(hash::hash(input.as_bytes()));
+	let input = SecretBytes::concat(&[id.as_bytes(), modifier.as_bytes()]);
+	let hash = encode(hash::hash(&input));
 	Ok(hash)
 }
 
-// hash with sha256 to get next id-seed or aes-key-seed, used for Perfect Forward Secrecy
-pub fn get_next(current: &str, salt: &str) -> Result<String, String> {
+// derive the next id seed using the HKDF key schedule, used for Perfect Forward Secrecy
+pub fn get_next(current: &str, salt: &str) -> Result<String, DawnCryptoError> {
 	if !IS_ID_SEED.is_match(current) {
-		return Err("invalid id".to_string())
+		return Err(DawnCryptoError::InvalidId)
 	}
 	if !IS_SALT.is_match(salt) {
-		return Err("invalid salt".to_string())
+		return Err(DawnCryptoError::InvalidSalt)
 	}
-	let mut hash_input = current.as_bytes().to_vec();
-	hash_input.append(&mut salt.as_bytes().to_vec());
-	let hash = encode(hash::hash(&hash_input));
-	Ok(hash)
+	let current = SecretBytes::from(decode(current).map_err(|_| DawnCryptoError::InvalidId)?);
+	let salt = decode(salt).map_err(|_| DawnCryptoError::InvalidSalt)?;
+	Ok(encode(kdf::next_id_seed(&current, &salt)?))
+}
+
+// hash with sha256 to get next id-seed, only for chats set up before the key schedule
+pub fn get_next_legacy(current: &str, salt: &str) -> Result<String, DawnCryptoError> {
+	if !IS_ID_SEED.is_match(current) {
+		return Err(DawnCryptoError::InvalidId)
+	}
+	if !IS_SALT.is_match(salt) {
+		return Err(DawnCryptoError::InvalidSalt)
+	}
+	Ok(encode(kdf::next_id_seed_legacy(current.as_bytes(), salt.as_bytes())))
 }
 
 
 // this returns the current timestamp
-pub fn get_current_timestamp() -> Result<String, String> {
+pub fn get_current_timestamp() -> Result<String, DawnCryptoError> {
 	// get current time
 	let c_time = Utc::now();
 	let date_modifier = c_time.date_naive().format("%Y%m%d").to_string();
 	let time_modifier = c_time.time().format("%H").to_string().parse::<u8>();
 	if time_modifier.is_err() {
-		return Err("failed to format time".to_string());
+		return Err(DawnCryptoError::InvalidTimestamp("failed to format time"));
 	}
 	
 	// round to 4-hour resolution
@@ -103,15 +120,12 @@ pub fn get_current_timestamp() -> Result<String, String> {
 }
 
 // this returns a list of all timestamps from the given input timestamp until the current timestamp
-pub fn get_all_timestamps_since(timestamp: &str) -> Result<Vec<String>, String> {
-	let time = match parse_timestamp(timestamp) {
-		Ok(res) => res,
-		Err(err) => return Err(err)
-	};
+pub fn get_all_timestamps_since(timestamp: &str) -> Result<Vec<String>, DawnCryptoError> {
+	let time = parse_timestamp(timestamp)?;
 	
 	let current_time = Utc::now().naive_utc();
 	
-	if time > current_time { return Err("timestamp is in the future".to_string()); }
+	if time > current_time { return Err(DawnCryptoError::InvalidTimestamp("timestamp is in the future")); }
 	
 	let mut timestamps = Vec::<String>::new();
 	let mut time_to_check = time;
@@ -126,22 +140,22 @@ pub fn get_all_timestamps_since(timestamp: &str) -> Result<Vec<String>, String>
 	Ok(timestamps)
 }
 
-fn parse_timestamp(timestamp: &str) -> Result<NaiveDateTime, String> {
+pub fn parse_timestamp(timestamp: &str) -> Result<NaiveDateTime, DawnCryptoError> {
 	if timestamp.len() != 9 {
-		return Err("invalid timestamp length".to_string());
+		return Err(DawnCryptoError::InvalidTimestamp("invalid timestamp length"));
 	}
 	
 	let timestamp_date = match NaiveDate::parse_from_str(&timestamp[0..8], "%Y%m%d") {
 		Ok(res) => res,
-		Err(_) => return Err("failed to parse the date".to_string())
+		Err(_) => return Err(DawnCryptoError::InvalidTimestamp("failed to parse the date"))
 	};
 	let timestamp_hour = match &timestamp[8..9].parse::<u32>() { // parsing as u32 because chrono's and_hms_opt requires this as input. Otherwise, u8 would be fine obviously
 		Ok(res) => 4 * res,
-		Err(_) => return Err("failed to parse the time modifier".to_string())
+		Err(_) => return Err(DawnCryptoError::InvalidTimestamp("failed to parse the time modifier"))
 	};
 	let time = match timestamp_date.and_hms_opt(timestamp_hour, 0, 0) {
 		Some(res) => res,
-		None => return Err("failed to add the time modifier".to_string())
+		None => return Err(DawnCryptoError::InvalidTimestamp("failed to add the time modifier"))
 	};
 	Ok(time)
 }
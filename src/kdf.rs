@@ -0,0 +1,109 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// key schedule based on HKDF-SHA256
+// Every derived value gets its own label, so a value derived for one purpose can never be the same as a value derived
+// for another purpose. Inputs are framed with their length, so different inputs can't result in the same HKDF input.
+// The functions deriving the same values by hashing the concatenated inputs are kept for compatibility and end in _legacy.
+
+use crate::encoding::push_field;
+use crate::error::DawnCryptoError;
+use crate::hash;
+use crate::secret::SecretBytes;
+
+const SALT_1_LABEL: &[u8] = b"dawn-crypto salt 1";
+const SALT_2_LABEL: &[u8] = b"dawn-crypto salt 2";
+const PFS_KEY_LABEL: &[u8] = b"dawn-crypto pfs key";
+const MESSAGE_KEY_LABEL: &[u8] = b"dawn-crypto message key";
+const ID_SEED_LABEL: &[u8] = b"dawn-crypto id seed";
+const MDC_LABEL: &[u8] = b"dawn-crypto mdc";
+const CONVERGENT_KEY_LABEL: &[u8] = b"dawn-crypto convergent key";
+const SIV_KEY_LABEL: &[u8] = b"dawn-crypto siv key";
+const SIV_NONCE_LABEL: &[u8] = b"dawn-crypto siv nonce";
+
+fn derive(inputs: &[&[u8]], salt: &[u8], label: &[u8], len: usize) -> Result<SecretBytes, DawnCryptoError> {
+	let mut ikm = Vec::with_capacity(inputs.iter().map(|input| 4 + input.len()).sum());
+	for input in inputs {
+		push_field(&mut ikm, input);
+	}
+	let ikm = SecretBytes::from(ikm);
+	hash::hkdf(&ikm, salt, label, len).map_err(DawnCryptoError::KeyDerivation)
+}
+
+pub fn derive_salts(kyber_secret: &[u8], curve_secret: &[u8]) -> Result<(SecretBytes, SecretBytes), DawnCryptoError> {
+	let salt1 = derive(&[kyber_secret, curve_secret], &[], SALT_1_LABEL, 32)?;
+	let salt2 = derive(&[kyber_secret, curve_secret], &[], SALT_2_LABEL, 32)?;
+	Ok((salt1, salt2))
+}
+
+pub fn derive_salts_legacy(kyber_secret: &[u8], curve_secret: &[u8]) -> (SecretBytes, SecretBytes) {
+	let salt1 = hash::hash_secret(&SecretBytes::concat(&[kyber_secret, curve_secret]));
+	let salt2 = hash::hash_secret(&SecretBytes::concat(&[curve_secret, kyber_secret]));
+	(salt1, salt2)
+}
+
+// next key of the PFS chain
+pub fn get_pfs_key(pfs_key: &[u8], salt: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	derive(&[pfs_key], salt, PFS_KEY_LABEL, 32)
+}
+
+pub fn get_pfs_key_legacy(pfs_key: &[u8], salt: &[u8]) -> SecretBytes {
+	hash::hash_secret(&SecretBytes::concat(&[pfs_key, salt]))
+}
+
+// key for encrypting a message from the KEM shared secret and the new PFS key
+pub fn message_key(kem_shared_secret: &[u8], pfs_key: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	derive(&[kem_shared_secret, pfs_key], &[], MESSAGE_KEY_LABEL, 32)
+}
+
+pub fn message_key_legacy(kem_shared_secret: &[u8], pfs_key: &[u8]) -> SecretBytes {
+	hash::hash_secret(&SecretBytes::concat(&[kem_shared_secret, pfs_key]))
+}
+
+// next id seed
+pub fn next_id_seed(id_seed: &[u8], salt: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	derive(&[id_seed], salt, ID_SEED_LABEL, 32)
+}
+
+pub fn next_id_seed_legacy(id_seed: &[u8], salt: &[u8]) -> SecretBytes {
+	hash::hash_secret(&SecretBytes::concat(&[id_seed, salt]))
+}
+
+// 4 bytes of message detail code
+pub fn mdc(mdc_seed: &[u8], temp_id: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	derive(&[mdc_seed, temp_id], &[], MDC_LABEL, 4)
+}
+
+pub fn mdc_legacy(mdc_seed: &[u8], temp_id: &[u8]) -> Vec<u8> {
+	hash::hash(&SecretBytes::concat(&[mdc_seed, temp_id]))[..4].to_vec()
+}
+
+// key for convergent encryption from the hash of the content and the convergence secret of a user or group
+pub fn convergent_key(content_hash: &[u8], convergence_secret: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	derive(&[content_hash], convergence_secret, CONVERGENT_KEY_LABEL, 32)
+}
+
+// key for the cipher of deterministic encryption, so the nonces are derived using an independent key
+pub fn siv_key(key: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	derive(&[key], &[], SIV_KEY_LABEL, 32)
+}
+
+// synthetic nonce of deterministic encryption, depending on the key, the associated data and the plaintext
+pub fn siv_nonce(key: &[u8], aad: &[u8], data: &[u8], len: usize) -> Result<SecretBytes, DawnCryptoError> {
+	derive(&[aad, data], key, SIV_NONCE_LABEL, len)
+}
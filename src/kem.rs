@@ -0,0 +1,136 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// selection of the post-quantum key encapsulation mechanism used for messages
+// keys get encoded as algorithm id || raw key. Kyber1024 keys created before the algorithm could be selected
+// carry no id, they are recognized by their length (which differs from all tagged encodings).
+// The ciphertext is tagged by the algorithm suite of the message envelope, which uses the same ids.
+
+use crate::envelope;
+use crate::error::DawnCryptoError;
+use crate::kyber;
+use crate::mlkem::{mlkem768, mlkem1024};
+use crate::secret::SecretBytes;
+
+type KemResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KemAlgorithm {
+	// round-3 Kyber, kept for existing keys
+	Kyber1024,
+	MlKem768,
+	MlKem1024,
+}
+
+impl KemAlgorithm {
+	pub fn id(self) -> u8 {
+		match self {
+			KemAlgorithm::Kyber1024 => envelope::KEM_KYBER1024,
+			KemAlgorithm::MlKem768 => envelope::KEM_MLKEM768,
+			KemAlgorithm::MlKem1024 => envelope::KEM_MLKEM1024,
+		}
+	}
+	
+	pub fn from_id(id: u8) -> Option<KemAlgorithm> {
+		match id {
+			envelope::KEM_KYBER1024 => Some(KemAlgorithm::Kyber1024),
+			envelope::KEM_MLKEM768 => Some(KemAlgorithm::MlKem768),
+			envelope::KEM_MLKEM1024 => Some(KemAlgorithm::MlKem1024),
+			_ => None
+		}
+	}
+	
+	// lengths of the raw public and secret key
+	fn key_lengths(self) -> (usize, usize) {
+		match self {
+			KemAlgorithm::Kyber1024 => (LEGACY_PUBLIC_KEY_LEN, LEGACY_SECRET_KEY_LEN),
+			KemAlgorithm::MlKem768 => mlkem768::key_lengths(),
+			KemAlgorithm::MlKem1024 => mlkem1024::key_lengths(),
+		}
+	}
+}
+
+const LEGACY_PUBLIC_KEY_LEN: usize = 1568;
+const LEGACY_SECRET_KEY_LEN: usize = 3168;
+
+// returns the tagged public and secret key
+pub fn keygen(algorithm: KemAlgorithm) -> (Vec<u8>, SecretBytes) {
+	let (pk, sk) = match algorithm {
+		KemAlgorithm::Kyber1024 => kyber::keygen(),
+		KemAlgorithm::MlKem768 => mlkem768::keygen(),
+		KemAlgorithm::MlKem1024 => mlkem1024::keygen(),
+	};
+	([&[algorithm.id()], &pk[..]].concat(), SecretBytes::concat(&[&[algorithm.id()], &sk]))
+}
+
+fn decode_key(key: &[u8], legacy_len: usize, secret: bool) -> Result<(KemAlgorithm, &[u8]), DawnCryptoError> {
+	if key.len() == legacy_len { return Ok((KemAlgorithm::Kyber1024, key)) }
+	let (id, raw_key) = match key.split_first() {
+		Some(res) => res,
+		None => return Err(DawnCryptoError::InvalidKeyLength { expected: legacy_len, actual: 0 })
+	};
+	let algorithm = match KemAlgorithm::from_id(*id) {
+		Some(res) => res,
+		None => return Err(DawnCryptoError::UnsupportedAlgorithm(*id))
+	};
+	let (public_key_len, secret_key_len) = algorithm.key_lengths();
+	let expected = if secret { secret_key_len } else { public_key_len };
+	if raw_key.len() != expected {
+		return Err(DawnCryptoError::InvalidKeyLength { expected: expected + 1, actual: key.len() })
+	}
+	Ok((algorithm, raw_key))
+}
+
+// split an encoded public key into its algorithm and the raw key
+pub fn decode_public_key(pub_key: &[u8]) -> Result<(KemAlgorithm, &[u8]), DawnCryptoError> {
+	decode_key(pub_key, LEGACY_PUBLIC_KEY_LEN, false)
+}
+
+// split an encoded secret key into its algorithm and the raw key
+pub fn decode_secret_key(sec_key: &[u8]) -> Result<(KemAlgorithm, &[u8]), DawnCryptoError> {
+	decode_key(sec_key, LEGACY_SECRET_KEY_LEN, true)
+}
+
+// the following functions take raw keys, the ciphertexts are raw as well
+pub fn get_shared_secret(algorithm: KemAlgorithm, pub_key: &[u8]) -> KemResult<(SecretBytes, Vec<u8>)> {
+	match algorithm {
+		KemAlgorithm::Kyber1024 => kyber::get_shared_secret(pub_key),
+		KemAlgorithm::MlKem768 => mlkem768::get_shared_secret(pub_key),
+		KemAlgorithm::MlKem1024 => mlkem1024::get_shared_secret(pub_key),
+	}
+}
+
+pub fn decrypt_shared_secret(algorithm: KemAlgorithm, ciphertext: &[u8], sec_key: &[u8]) -> KemResult<SecretBytes> {
+	match algorithm {
+		KemAlgorithm::Kyber1024 => kyber::decrypt_shared_secret(ciphertext, sec_key),
+		KemAlgorithm::MlKem768 => mlkem768::decrypt_shared_secret(ciphertext, sec_key),
+		KemAlgorithm::MlKem1024 => mlkem1024::decrypt_shared_secret(ciphertext, sec_key),
+	}
+}
+
+// recover the encoded public key from an encoded secret key, keeping the legacy encoding for legacy keys
+pub fn public_key_from_secret(sec_key: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+	let (algorithm, raw_key) = decode_secret_key(sec_key)?;
+	let public_key = match algorithm {
+		KemAlgorithm::Kyber1024 => kyber::public_key_from_secret(raw_key),
+		KemAlgorithm::MlKem768 => mlkem768::public_key_from_secret(raw_key),
+		KemAlgorithm::MlKem1024 => mlkem1024::public_key_from_secret(raw_key),
+	}.map_err(DawnCryptoError::InvalidKey)?;
+	if sec_key.len() == LEGACY_SECRET_KEY_LEN { return Ok(public_key) }
+	Ok([&[algorithm.id()], &public_key[..]].concat())
+}
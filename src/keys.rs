@@ -0,0 +1,176 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// typed keys, so keys of different kinds can't be mixed up
+// every type is only constructed from bytes that passed validation, and secret keys are stored as SecretBytes
+
+use crate::error::DawnCryptoError;
+use crate::kem::{self, KemAlgorithm};
+use crate::secret::SecretBytes;
+use crate::sign::{self, SignatureAlgorithm};
+
+fn check_length(bytes: &[u8], expected: usize) -> Result<(), DawnCryptoError> {
+	match bytes.len() == expected {
+		true => Ok(()),
+		false => Err(DawnCryptoError::InvalidKeyLength { expected, actual: bytes.len() })
+	}
+}
+
+// public key for key encapsulation (Kyber1024 or ML-KEM)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KyberPublicKey(Vec<u8>);
+
+impl KyberPublicKey {
+	pub(crate) fn new(bytes: Vec<u8>) -> KyberPublicKey {
+		KyberPublicKey(bytes)
+	}
+	
+	pub fn from_bytes(bytes: &[u8]) -> Result<KyberPublicKey, DawnCryptoError> {
+		kem::decode_public_key(bytes)?;
+		Ok(KyberPublicKey(bytes.to_vec()))
+	}
+	
+	pub fn algorithm(&self) -> KemAlgorithm {
+		kem::decode_public_key(&self.0).unwrap().0
+	}
+	
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+// secret key for key encapsulation (Kyber1024 or ML-KEM)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KyberSecretKey(SecretBytes);
+
+impl KyberSecretKey {
+	pub(crate) fn new(bytes: SecretBytes) -> KyberSecretKey {
+		KyberSecretKey(bytes)
+	}
+	
+	pub fn from_bytes(bytes: &[u8]) -> Result<KyberSecretKey, DawnCryptoError> {
+		kem::decode_secret_key(bytes)?;
+		Ok(KyberSecretKey(SecretBytes::from_slice(bytes)))
+	}
+	
+	pub fn algorithm(&self) -> KemAlgorithm {
+		kem::decode_secret_key(&self.0).unwrap().0
+	}
+	
+	pub fn public_key(&self) -> Result<KyberPublicKey, DawnCryptoError> {
+		Ok(KyberPublicKey(kem::public_key_from_secret(&self.0)?))
+	}
+	
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+// secret key for signing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SigningKey(SecretBytes);
+
+impl SigningKey {
+	pub(crate) fn new(bytes: SecretBytes) -> SigningKey {
+		SigningKey(bytes)
+	}
+	
+	pub fn from_bytes(bytes: &[u8]) -> Result<SigningKey, DawnCryptoError> {
+		sign::secret_key_algorithm(bytes)?;
+		Ok(SigningKey(SecretBytes::from_slice(bytes)))
+	}
+	
+	pub fn algorithm(&self) -> SignatureAlgorithm {
+		sign::secret_key_algorithm(&self.0).unwrap()
+	}
+	
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+// public key for verifying signatures
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKey(Vec<u8>);
+
+impl VerifyingKey {
+	pub(crate) fn new(bytes: Vec<u8>) -> VerifyingKey {
+		VerifyingKey(bytes)
+	}
+	
+	pub fn from_bytes(bytes: &[u8]) -> Result<VerifyingKey, DawnCryptoError> {
+		sign::public_key_algorithm(bytes)?;
+		Ok(VerifyingKey(bytes.to_vec()))
+	}
+	
+	pub fn algorithm(&self) -> SignatureAlgorithm {
+		sign::public_key_algorithm(&self.0).unwrap()
+	}
+	
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+// x25519 public key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurvePublicKey(Vec<u8>);
+
+impl CurvePublicKey {
+	pub(crate) fn new(bytes: Vec<u8>) -> CurvePublicKey {
+		CurvePublicKey(bytes)
+	}
+	
+	pub fn from_bytes(bytes: &[u8]) -> Result<CurvePublicKey, DawnCryptoError> {
+		check_length(bytes, 32)?;
+		Ok(CurvePublicKey(bytes.to_vec()))
+	}
+	
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+// the remaining types are all secrets of 32 bytes
+macro_rules! secret_key_type {
+	($name:ident) => {
+		#[derive(Clone, Debug, PartialEq, Eq)]
+		pub struct $name(SecretBytes);
+		
+		impl $name {
+			pub fn from_bytes(bytes: &[u8]) -> Result<$name, DawnCryptoError> {
+				check_length(bytes, 32)?;
+				Ok($name(SecretBytes::from_slice(bytes)))
+			}
+			
+			// for keys generated by this library, which don't need to be validated
+			pub(crate) fn new(secret: SecretBytes) -> $name {
+				$name(secret)
+			}
+			
+			pub fn as_bytes(&self) -> &[u8] {
+				&self.0
+			}
+		}
+	};
+}
+
+secret_key_type!(CurveSecretKey);
+secret_key_type!(PfsKey);
+secret_key_type!(Salt);
+secret_key_type!(SymmetricKey);
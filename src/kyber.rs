@@ -19,23 +19,22 @@
 // the traits below just need to be in scope, the name doesn't matter but can't be ambigous
 use pqcrypto_traits::kem::{PublicKey as _, SecretKey as _, SharedSecret as _, Ciphertext as _};
 use pqcrypto_kyber::kyber1024::*;
+use crate::secret::SecretBytes;
 
-pub fn keygen() -> (Vec<u8>, Vec<u8>) {
+pub fn keygen() -> (Vec<u8>, SecretBytes) {
 	let (pk, sk) = keypair();
 	let public_key = pk.as_bytes().to_vec();
-	let secret_key = sk.as_bytes().to_vec();
+	let secret_key = SecretBytes::from_slice(sk.as_bytes());
 	(public_key, secret_key)
 }
 
-pub fn get_shared_secret(pub_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn std::error::Error>> {
+pub fn get_shared_secret(pub_key: &[u8]) -> Result<(SecretBytes, Vec<u8>), Box<dyn std::error::Error + Send + Sync>> {
 	
 	// import public key
 	let pk = PublicKey::from_bytes(pub_key)?;
 	
 	let (shared_secret, ciphertext) = encapsulate(&pk);
-	let shared_secret = shared_secret
-		.as_bytes()
-		.to_vec();
+	let shared_secret = SecretBytes::from_slice(shared_secret.as_bytes());
 	let ciphertext = ciphertext
 		.as_bytes()
 		.to_vec();
@@ -44,7 +43,7 @@ pub fn get_shared_secret(pub_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn s
 	
 }
 
-pub fn decrypt_shared_secret(ciphertext: &[u8], sec_key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+pub fn decrypt_shared_secret(ciphertext: &[u8], sec_key: &[u8]) -> Result<SecretBytes, Box<dyn std::error::Error + Send + Sync>> {
 	
 	// import ciphertext
 	let ciphertext = Ciphertext::from_bytes(ciphertext)?;
@@ -52,5 +51,12 @@ pub fn decrypt_shared_secret(ciphertext: &[u8], sec_key: &[u8]) -> Result<Vec<u8
 	// import secret key
 	let sk = SecretKey::from_bytes(sec_key)?;
 	
-	Ok(decapsulate(&ciphertext, &sk).as_bytes().to_vec())
+	Ok(SecretBytes::from_slice(decapsulate(&ciphertext, &sk).as_bytes()))
+}
+
+// the secret key contains the public key (after the 1536 bytes of the secret polynomial vector), so it can be recovered from it
+pub fn public_key_from_secret(sec_key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	let sk = SecretKey::from_bytes(sec_key)?;
+	let public_key = sk.as_bytes()[1536..1536 + public_key_bytes()].to_vec();
+	Ok(public_key)
 }
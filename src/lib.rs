@@ -16,11 +16,34 @@
 	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
 */
 
+mod compat;
+mod container;
+mod convergent;
+pub mod debug;
+mod ed25519;
+mod encoding;
+mod envelope;
+mod error;
 mod hash;
+mod hybrid;
 mod id;
+mod kdf;
+mod kem;
+mod keys;
 mod kyber;
+mod mldsa;
+mod mlkem;
+mod padding;
+mod password;
+mod secret;
+mod ratchet;
+mod session;
 mod sign;
+mod sphincs;
+mod storage;
+mod stream;
 mod symm;
+pub mod typed;
 pub mod warning;
 mod x25519;
 
@@ -31,67 +54,81 @@ extern crate lazy_static;
 mod tests;
 
 use sign::*;
-use hex::{encode, decode};
+use hex::encode;
 use rand::Rng;
 use crate::warning::*;
-pub use hash::hash;
+use zeroize::Zeroize;
+pub use error::DawnCryptoError;
+pub use envelope::MessageEnvelope;
+pub use kem::KemAlgorithm;
+pub use padding::Padding;
+pub use keys::{KyberPublicKey, KyberSecretKey, SigningKey, VerifyingKey, CurvePublicKey, CurveSecretKey, PfsKey, Salt, SymmetricKey};
+pub use secret::SecretBytes;
+pub use ratchet::Ratchet;
+pub use session::Session;
+pub use sign::SignatureAlgorithm;
+pub use storage::{RecordType, seal_session, open_session};
+pub use hash::{hash, Argon2Params};
+pub use symm::SymmetricCipher;
+pub use stream::{StreamEncryptor, StreamDecryptor};
+pub use container::{ContainerReader, encrypt_container, encrypt_container_with_chunk_size};
 pub use id::get_current_timestamp;
 pub use id::get_all_timestamps_since;
 
-// Error return macro
-macro_rules! error{
-	($a:expr) => {
-		return Err(String::from("@dawn-crypto: ") + &$a)
+// This returns a tuple with the public and secret key that got generated (for encrypting)
+pub fn kyber_keygen() -> (Vec<u8>, SecretBytes) {
+	kyber::keygen()
+}
+
+// This returns a tuple with the public and secret key of the selected algorithm (for encrypting)
+// the keys are tagged with the algorithm, so they can be used with all functions that take a kyber key
+pub fn kem_keygen(algorithm: KemAlgorithm) -> (Vec<u8>, SecretBytes) {
+	kem::keygen(algorithm)
+}
+
+// This returns the algorithm of a public or secret key for encrypting, so keys in need of migration can be found
+pub fn get_kem_algorithm(key: &[u8]) -> Result<KemAlgorithm, DawnCryptoError> {
+	match kem::decode_public_key(key) {
+		Ok((algorithm, _)) => Ok(algorithm),
+		Err(_) => Ok(kem::decode_secret_key(key)?.0)
 	}
 }
 
-// This returns a tuple with the public and secret key that got generated (for encrypting)
-pub fn kyber_keygen() -> (Vec<u8>, Vec<u8>) {
-	kyber::keygen()
+// This returns a tuple with the public and secret key of the selected algorithm that got generated (for signing)
+pub fn sign_keygen(algorithm: SignatureAlgorithm) -> (Vec<u8>, SecretBytes) {
+	sign::keygen(algorithm)
 }
 
-// This returns a tuple with the public and secret key that got generated (for signing)
-pub fn sign_keygen() -> (Vec<u8>, Vec<u8>) {
-	sign::keygen()
+// This returns the algorithm of a public or secret key for signing
+pub fn get_signature_algorithm(key: &[u8]) -> Result<SignatureAlgorithm, DawnCryptoError> {
+	sign::key_algorithm(key)
 }
 
 // This returns a tuple with the public and secret key that got generated (for init, using x25519)
-pub fn curve_keygen() -> (Vec<u8>, Vec<u8>) {
+pub fn curve_keygen() -> (Vec<u8>, SecretBytes) {
 	x25519::keygen()
 }
 
 // This returns the shared secret derived from x25519 keys using Diffie-Hellman
-pub fn get_curve_secret(secret_key: &[u8], public_key: &[u8]) -> Result<Vec<u8>, String> {
-	match x25519::get_shared_secret(secret_key, public_key) {
-		Ok(res) => Ok(res),
-		Err(_) => {
-			error!("failed to derive curve secret");
-		}
-	}
+pub fn get_curve_secret(secret_key: &[u8], public_key: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	x25519::get_shared_secret(secret_key, public_key).map_err(DawnCryptoError::KeyAgreement)
 }
 
 // This returns the shared secret and ciphertext from a kyber public key
-pub fn get_kyber_secret(public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
-	match kyber::get_shared_secret(public_key) {
-		Ok(res) => Ok(res),
-		Err(_) => {
-			error!("failed to derive kyber secret");
-		}
-	}
+pub fn get_kyber_secret(public_key: &[u8]) -> Result<(SecretBytes, Vec<u8>), DawnCryptoError> {
+	let (algorithm, public_key) = kem::decode_public_key(public_key)?;
+	kem::get_shared_secret(algorithm, public_key).map_err(DawnCryptoError::KemEncapsulation)
 }
 
 // This returns the shared secret from a kyber ciphertext and the corresponding secret key
-pub fn decrypt_kyber_secret(ciphertext: &[u8], secret_key: &[u8]) -> Result<Vec<u8>, String> {
-	match kyber::decrypt_shared_secret(ciphertext, secret_key) {
-		Ok(res) => Ok(res),
-		Err(_) => {
-			error!("failed to derive kyber secret");
-		}
-	}
+pub fn decrypt_kyber_secret(ciphertext: &[u8], secret_key: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	let (algorithm, secret_key) = kem::decode_secret_key(secret_key)?;
+	kem::decrypt_shared_secret(algorithm, ciphertext, secret_key).map_err(DawnCryptoError::KemDecapsulation)
 }
 
 // This is a convenience function to generate the keypairs and an id at the same time
-pub fn init() -> ((Vec<u8>, Vec<u8>), (Vec<u8>, Vec<u8>), (Vec<u8>, Vec<u8>), (Vec<u8>, Vec<u8>), String) {
+#[allow(clippy::type_complexity)]
+pub fn init() -> ((Vec<u8>, SecretBytes), (Vec<u8>, SecretBytes), (Vec<u8>, SecretBytes), (Vec<u8>, SecretBytes), String) {
 	let keypair_kyber = kyber::keygen();
 	let keypair_curve = x25519::keygen();
 	let keypair_kyber_for_salt = kyber::keygen();
@@ -100,17 +137,19 @@ pub fn init() -> ((Vec<u8>, Vec<u8>), (Vec<u8>, Vec<u8>), (Vec<u8>, Vec<u8>), (V
 	(keypair_kyber, keypair_curve, keypair_kyber_for_salt, keypair_curve_for_salt, id)
 }
 
-pub fn derive_salts(kyber_secret: &[u8], curve_secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
-	if kyber_secret.len() != 32 || curve_secret.len() != 32 { return Err("invalid secret length".to_string()) }
-	let mut kyber_secret_a = kyber_secret.to_vec();
-	let mut kyber_secret_b = kyber_secret_a.clone();
-	let mut curve_secret_a = curve_secret.to_vec();
-	let mut curve_secret_b = curve_secret_a.clone();
-	kyber_secret_a.append(&mut curve_secret_a);
-	curve_secret_b.append(&mut kyber_secret_b);
-	let salt1 = hash::hash(&kyber_secret_a);
-	let salt2 = hash::hash(&curve_secret_b);
-	Ok((salt1, salt2))
+// derive the PFS salts of both directions from the kyber and curve secrets exchanged when initializing a chat
+pub fn derive_salts(kyber_secret: &[u8], curve_secret: &[u8]) -> Result<(SecretBytes, SecretBytes), DawnCryptoError> {
+	if kyber_secret.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: kyber_secret.len() }) }
+	if curve_secret.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: curve_secret.len() }) }
+	kdf::derive_salts(kyber_secret, curve_secret)
+}
+
+// derive the PFS salts by hashing the concatenated secrets, only for chats set up before the key schedule
+// both sides of a chat need to use the same function, the salts differ from the ones of derive_salts
+pub fn derive_salts_legacy(kyber_secret: &[u8], curve_secret: &[u8]) -> Result<(SecretBytes, SecretBytes), DawnCryptoError> {
+	if kyber_secret.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: kyber_secret.len() }) }
+	if curve_secret.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: curve_secret.len() }) }
+	Ok(kdf::derive_salts_legacy(kyber_secret, curve_secret))
 }
 
 // generate an id
@@ -127,177 +166,371 @@ pub fn mdc_gen() -> String {
 }
 
 // generate predictable message detail code using seed and temporary ID
-pub fn predictable_mdc_gen(mdc_seed: &str, temp_id: &str) -> String {
-	let mut hash_input = mdc_seed.as_bytes().to_vec();
-	hash_input.append(&mut temp_id.as_bytes().to_vec());
-	let output = encode(hash::hash(&hash_input));
-	(&output[0..8]).to_string()
+pub fn predictable_mdc_gen(mdc_seed: &str, temp_id: &str) -> Result<String, DawnCryptoError> {
+	Ok(encode(kdf::mdc(mdc_seed.as_bytes(), temp_id.as_bytes())?))
+}
+
+// generate predictable message detail code by hashing the concatenated inputs, only for chats set up before the key schedule
+pub fn predictable_mdc_gen_legacy(mdc_seed: &str, temp_id: &str) -> String {
+	encode(kdf::mdc_legacy(mdc_seed.as_bytes(), temp_id.as_bytes()))
 }
 
 // generate a key for symmetric encryption (e.g. for sending files) using a CSPRNG
-pub fn sym_key_gen() -> Vec<u8> {
-	rand::thread_rng()
-		.gen::<[u8; 32]>()
-		.to_vec()
+pub fn sym_key_gen() -> SecretBytes {
+	let mut key = rand::thread_rng().gen::<[u8; 32]>();
+	let secret = SecretBytes::from_slice(&key);
+	key.zeroize();
+	secret
 }
 
 // get a temporary id from a seed and the default modifier
-pub fn get_temp_id(id: &str) -> Result<String, String> {
+pub fn get_temp_id(id: &str) -> Result<String, DawnCryptoError> {
 	id::get_temp_id(id)
 }
 
 // get a temporary id from a seed and a modifier (e.g. time)
-pub fn get_custom_temp_id(id: &str, modifier: &str) -> Result<String, String> {
+pub fn get_custom_temp_id(id: &str, modifier: &str) -> Result<String, DawnCryptoError> {
 	id::get_custom_temp_id(id, modifier)
 }
 
 // get next id for PFS-based id generation
-pub fn get_next_id(id: &str, salt: &str) -> Result<String, String> {
+pub fn get_next_id(id: &str, salt: &str) -> Result<String, DawnCryptoError> {
 	id::get_next(id, salt)
 }
 
-// encrypt (and optionally sign) message
+// get next id by hashing the concatenated inputs, only for chats set up before the key schedule
+pub fn get_next_id_legacy(id: &str, salt: &str) -> Result<String, DawnCryptoError> {
+	id::get_next_legacy(id, salt)
+}
+
+// encrypt (and optionally sign) a text message
 // returns the encrypted and signed message and the new Perfect Forward Secrecy key on success
-pub fn encrypt_msg(pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
-
-	// get shared secret and ciphertext for kyber encryption
-	let (kyber_shared_secret, kyber_ciphertext) = match kyber::get_shared_secret(pub_key) {
-		Ok((shared_secret, ciphertext)) => {
-			(shared_secret, ciphertext)
-		}
-		Err(_) => {
-			error!("failed to get kyber shared secret")
-		}
-	};
+pub fn encrypt_msg(pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &str, cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	encrypt_msg_bytes(pub_key, sec_key, pfs_key, salt, msg.as_bytes(), cipher)
+}
+
+// decrypt a text message and optionally check signature
+// returns the message content and the new Perfect Forward Secrecy key on success. Also, there is a cumulative byte indicating warnings.
+pub fn decrypt_msg(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(String, SecretBytes, u8), DawnCryptoError> {
+	debug::decrypt_msg(sec_key, pub_key, pfs_key, salt, enc_msg).map_err(opaque_decryption_error)
+}
+
+// decrypt a text message like decrypt_msg, additionally accepting the aes-256-cbc format without envelope of earlier versions
+// legacy messages are flagged with the LEGACY_FORMAT warning. Their signatures aren't bound to the recipient, so a signed
+// legacy message is rejected if a public key for verification is given.
+pub fn decrypt_msg_compat(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(String, SecretBytes, u8), DawnCryptoError> {
+	debug::decrypt_msg_compat(sec_key, pub_key, pfs_key, salt, enc_msg).map_err(opaque_decryption_error)
+}
+
+// encrypt (and optionally sign) a message with arbitrary binary content
+// returns the encrypted and signed message and the new Perfect Forward Secrecy key on success
+// the message is padded using Padmé, so only a rough size of the message is revealed
+pub fn encrypt_msg_bytes(pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &[u8], cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	encrypt_message(false, pub_key, sec_key, pfs_key, salt, msg, Padding::Padme, cipher)
+}
+
+// encrypt (and optionally sign) a message with arbitrary binary content, using the selected padding scheme
+// the padding is removed by decrypt_msg_bytes, so no information about it is needed to decrypt the message
+#[allow(clippy::too_many_arguments)]
+pub fn encrypt_msg_bytes_padded(pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &[u8], padding: Padding, cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	encrypt_message(false, pub_key, sec_key, pfs_key, salt, msg, padding, cipher)
+}
+
+// decrypt a message with arbitrary binary content and optionally check signature
+// returns the message content and the new Perfect Forward Secrecy key on success. Also, there is a cumulative byte indicating warnings.
+pub fn decrypt_msg_bytes(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(Vec<u8>, SecretBytes, u8), DawnCryptoError> {
+	debug::decrypt_msg_bytes(sec_key, pub_key, pfs_key, salt, enc_msg).map_err(opaque_decryption_error)
+}
+
+// This returns a tuple with the public and secret key for hybrid encryption, combining x25519 and kyber
+pub fn hybrid_keygen() -> (Vec<u8>, SecretBytes) {
+	hybrid::keygen()
+}
+
+// encrypt (and optionally sign) a text message using the hybrid x25519 + kyber public key of the recipient
+// the message stays confidential as long as either x25519 or kyber isn't broken
+pub fn encrypt_msg_hybrid(pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &str, cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	encrypt_msg_bytes_hybrid(pub_key, sec_key, pfs_key, salt, msg.as_bytes(), cipher)
+}
+
+// decrypt a text message encrypted using a hybrid public key and optionally check signature
+pub fn decrypt_msg_hybrid(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(String, SecretBytes, u8), DawnCryptoError> {
+	debug::decrypt_msg_hybrid(sec_key, pub_key, pfs_key, salt, enc_msg).map_err(opaque_decryption_error)
+}
+
+// encrypt (and optionally sign) a message with arbitrary binary content using a hybrid public key
+pub fn encrypt_msg_bytes_hybrid(pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &[u8], cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	encrypt_message(true, pub_key, sec_key, pfs_key, salt, msg, Padding::Padme, cipher)
+}
+
+// encrypt (and optionally sign) a message with arbitrary binary content using a hybrid public key and the selected padding scheme
+#[allow(clippy::too_many_arguments)]
+pub fn encrypt_msg_bytes_hybrid_padded(pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &[u8], padding: Padding, cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	encrypt_message(true, pub_key, sec_key, pfs_key, salt, msg, padding, cipher)
+}
+
+// decrypt a message with arbitrary binary content encrypted using a hybrid public key and optionally check signature
+pub fn decrypt_msg_bytes_hybrid(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(Vec<u8>, SecretBytes, u8), DawnCryptoError> {
+	debug::decrypt_msg_bytes_hybrid(sec_key, pub_key, pfs_key, salt, enc_msg).map_err(opaque_decryption_error)
+}
+
+// failures caused by the received message are all reported as DecryptionFailed, so a client can't turn into an oracle
+// telling an attacker which part of a manipulated message was rejected. Only invalid lengths of the own keys, the PFS key
+// and the salt are reported in detail. The detailed errors are available through the functions in the debug module.
+fn opaque_decryption_error(err: DawnCryptoError) -> DawnCryptoError {
+	match err {
+		DawnCryptoError::InvalidKeyLength { .. } | DawnCryptoError::InvalidSalt => err,
+		_ => DawnCryptoError::DecryptionFailed
+	}
+}
+
+// get the kem id for the message envelope, the shared secret and the ciphertext for a hybrid or (tagged) kyber public key
+fn encapsulate(hybrid: bool, pub_key: &[u8]) -> Result<(u8, SecretBytes, Vec<u8>), DawnCryptoError> {
+	if hybrid {
+		let (shared_secret, ciphertext) = hybrid::get_shared_secret(pub_key).map_err(DawnCryptoError::KemEncapsulation)?;
+		return Ok((envelope::KEM_X25519_KYBER1024, shared_secret, ciphertext))
+	}
+	let (algorithm, pub_key) = kem::decode_public_key(pub_key)?;
+	let (shared_secret, ciphertext) = kem::get_shared_secret(algorithm, pub_key).map_err(DawnCryptoError::KemEncapsulation)?;
+	Ok((algorithm.id(), shared_secret, ciphertext))
+}
+
+fn decapsulate(hybrid: bool, ciphertext: &[u8], sec_key: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	if hybrid {
+		return hybrid::decrypt_shared_secret(ciphertext, sec_key).map_err(DawnCryptoError::KemDecapsulation)
+	}
+	let (algorithm, sec_key) = kem::decode_secret_key(sec_key)?;
+	kem::decrypt_shared_secret(algorithm, ciphertext, sec_key).map_err(DawnCryptoError::KemDecapsulation)
+}
+
+// derive the new PFS key and the message key
+fn message_keys(kem_shared_secret: &[u8], pfs_key: &[u8], salt: &[u8]) -> Result<(SecretBytes, SecretBytes), DawnCryptoError> {
+	let new_pfs_key = kdf::get_pfs_key(pfs_key, salt)?;
+	let secret = kdf::message_key(kem_shared_secret, &new_pfs_key)?;
+	Ok((new_pfs_key, secret))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn encrypt_message(hybrid: bool, pub_key: &[u8], sec_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], msg: &[u8], padding: Padding, cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+
+	// get shared secret and ciphertext for key encapsulation
+	let (kem, kem_shared_secret, kem_ciphertext) = encapsulate(hybrid, pub_key)?;
 	
 	// check key length
-	if pfs_key.len() != 32 { error!(format!("CRITICAL: PFS key has wrong length. Expected 32 bytes, got {} bytes", pfs_key.len())); }
+	if pfs_key.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: pfs_key.len() }) }
 	
 	// check salt length
-	if salt.len() != 32 { error!(format!("CRITICAL: salt has wrong length. Expected 32 bytes, got {} bytes", salt.len())); }
-	
-	// derive new Perfect Forward Secrecy key
-	let mut pfs_shared_secret = hash::get_pfs_key(pfs_key, salt);
-	let new_pfs_key = pfs_shared_secret.clone();
+	if salt.len() != 32 { return Err(DawnCryptoError::InvalidSalt) }
 	
-	// derive secret
-	let mut shared_secret = kyber_shared_secret.clone();
-	shared_secret.append(&mut pfs_shared_secret);
-	let secret = hash::hash(&shared_secret);
+	// derive new Perfect Forward Secrecy key and the message key
+	let (new_pfs_key, secret) = message_keys(&kem_shared_secret, pfs_key, salt)?;
 	
-	// sign the message if requested
-	let signature = if let Some(sec_key) = sec_key {
-		match sign(sec_key, msg) {
-			Ok(sig) => encode(sig),
-			Err(_) => {
-				error!("failed to sign message")
-			}
-		}
-	}
-	else {
-		"".to_string()
+	// sign the message together with its context if requested
+	let signature = match sec_key {
+		Some(sec_key) => sign(sec_key, &envelope::signed_data(kem, pub_key, &kem_ciphertext, &new_pfs_key, msg)?).map_err(DawnCryptoError::Signing)?,
+		None => vec![]
 	};
-	let signed_message_string = signature + "." + msg;
-	let signed_message = signed_message_string.as_bytes();
-	
-	// symmetric encryption of the message using the shared secret
-	let enc_msg = symm::encrypt(signed_message, &secret);
-	if enc_msg.is_err() { error!("symmetric encryption failed"); }
+	// pad the signed message, so the ciphertext doesn't reveal the exact length of the message
+	let signed_message = padding::pad(&envelope::encode_payload(&signature, msg), padding);
 	
-	let mut final_message = kyber_ciphertext;
-	final_message.append(&mut enc_msg.unwrap());
+	// authenticated symmetric encryption of the message using the shared secret, binding the envelope header and the kem ciphertext
+	let mut envelope = MessageEnvelope {
+		version: envelope::VERSION,
+		kem,
+		cipher,
+		kem_ciphertext,
+		payload: vec![],
+	};
+	envelope.payload = symm::encrypt_aead(&signed_message, &secret, &envelope.associated_data(), cipher).map_err(DawnCryptoError::SymmetricEncryption)?;
 	
-	Ok((final_message, new_pfs_key))
+	Ok((envelope.encode(), new_pfs_key))
 }
 
-// decrypt message and optionally check signature
-// returns the message content and the new Perfect Forward Secrecy key on success. Also, there is a cumulative byte indicating warnings.
-pub fn decrypt_msg(sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(String, Vec<u8>, u8), String> {
+fn decrypt_message(hybrid: bool, sec_key: &[u8], pub_key: Option<&[u8]>, pfs_key: &[u8], salt: &[u8], enc_msg: &[u8]) -> Result<(Vec<u8>, SecretBytes, u8), DawnCryptoError> {
 	
 	// initialize warnings
 	let mut warning = 0u8;
 	
-	// check message length
-	if enc_msg.len() <= 1568+16 { error!("message too short"); }
-	let mut enc_msg = enc_msg.to_vec();
+	// the message needs to use the algorithm of the secret key
+	let kem = match hybrid {
+		true => envelope::KEM_X25519_KYBER1024,
+		false => kem::decode_secret_key(sec_key)?.0.id()
+	};
+	
+	// check key length
+	if pfs_key.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: pfs_key.len() }) }
 	
 	// check salt length
-	if salt.len() != 32 { error!(format!("CRITICAL: salt has wrong length. Expected 32 bytes, got {} bytes", salt.len())); }
+	if salt.len() != 32 { return Err(DawnCryptoError::InvalidSalt) }
 	
-	// extract kyber ciphertext and symmetrically encrypted message
-	let symm_enc_msg = enc_msg.split_off(1568);
+	// extract kem ciphertext and symmetrically encrypted message
+	let envelope = MessageEnvelope::parse(enc_msg)?;
+	if envelope.kem != kem { return Err(DawnCryptoError::UnsupportedAlgorithm(envelope.suite())) }
 	
-	// decrypt kyber shared secret
-	let kyber_shared_secret = match kyber::decrypt_shared_secret(&enc_msg, sec_key) {
-		Ok(res) => res,
-		Err(_) => {
-			error!("could not decrypt kyber secret");
-		}
-	};
+	// decrypt kem shared secret
+	let kem_shared_secret = decapsulate(hybrid, &envelope.kem_ciphertext, sec_key)?;
 	
-	// check key length
-	if pfs_key.len() != 32 { error!(format!("CRITICAL: PFS key has wrong length. Expected 32 bytes, got {} bytes", pfs_key.len())); }
+	// derive new Perfect Forward Secrecy key and the message key
+	let (new_pfs_key, secret) = message_keys(&kem_shared_secret, pfs_key, salt)?;
 	
-	// derive new Perfect Forward Secrecy key
-	let mut pfs_shared_secret = hash::get_pfs_key(pfs_key, salt);
-	let new_pfs_key = pfs_shared_secret.clone();
+	// decrypt message, which also authenticates it together with the envelope header
+	let dec_msg = symm::decrypt_aead(&envelope.payload, &secret, &envelope.associated_data(), envelope.cipher).map_err(DawnCryptoError::SymmetricDecryption)?;
 	
-	// derive secret
-	let mut shared_secret = kyber_shared_secret.clone();
-	shared_secret.append(&mut pfs_shared_secret);
-	let secret = hash::hash(&shared_secret);
-	
-	// decrypt message
-	let dec_msg = symm::decrypt(&symm_enc_msg, &secret);
-	if dec_msg.is_err() { error!("symmetric decryption failed"); }
-	let dec_msg = dec_msg.unwrap();
+	// remove the padding, which is only checked after the message was authenticated
+	let dec_msg = match padding::unpad(&dec_msg) {
+		Some(unpadded) => unpadded,
+		None => return Err(DawnCryptoError::InvalidMessage("invalid padding"))
+	};
 	
 	// split signature and message
-	let signed_msg_string = String::from_utf8_lossy(&dec_msg);
-	let (signature, message) = match signed_msg_string.split_once('.') {
-		Some((sig, msg)) => {
-			// since signatures are optional, handle a missing signature gracefully
-			if sig.is_empty() {
-				warning += NO_SIGNATURE;
-				return Ok((msg.to_string(), new_pfs_key, warning))
-			}
-			(decode(sig), msg)
-		},
-		None => { error!("signature not found"); }
-	};
-	let signature = match signature {
-		Ok(sig) => sig,
-		Err(_) => { error!("signature parsing failed"); }
-	};
+	let (signature, message) = envelope::decode_payload(dec_msg)?;
+	
+	// since signatures are optional, handle a missing signature gracefully
+	if signature.is_empty() {
+		warning += NO_SIGNATURE;
+		return Ok((message, new_pfs_key, warning))
+	}
 	
 	// verify signature if requested
-	if pub_key.is_some() && verify(&signature, pub_key.unwrap(), message).is_err() {
-		error!("signature verification failed");
+	if let Some(pub_key) = pub_key {
+		let own_pub_key = match hybrid {
+			true => hybrid::public_key_from_secret(sec_key).map_err(DawnCryptoError::InvalidKey)?,
+			false => kem::public_key_from_secret(sec_key)?
+		};
+		let signed_data = envelope::signed_data(envelope.kem, &own_pub_key, &envelope.kem_ciphertext, &new_pfs_key, &message)?;
+		verify(&signature, pub_key, &signed_data).map_err(DawnCryptoError::SignatureInvalid)?;
 	}
 	
 	// return the message and new PFS key
-	Ok((message.to_string(), new_pfs_key, warning))
+	Ok((message, new_pfs_key, warning))
+}
+
+// encrypt data using a symmetric key and the selected cipher
+pub fn encrypt_data(data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	symm::encrypt_aead(data, key, &[], cipher).map_err(DawnCryptoError::SymmetricEncryption)
+}
+
+// decrypt data using a symmetric key and the cipher it was encrypted with
+// every failure is reported as DecryptionFailed, data encrypted by earlier versions using aes-256-cbc needs decrypt_data_compat
+pub fn decrypt_data(encrypted_data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	symm::decrypt_aead(encrypted_data, key, &[], cipher).map_err(|_| DawnCryptoError::DecryptionFailed)
+}
+
+// encrypt data (e.g. an attachment) using a symmetric key and the selected cipher, padding it first to hide its exact length
+// data encrypted this way needs to be decrypted using decrypt_data_padded
+pub fn encrypt_data_padded(data: &[u8], key: &[u8], padding: Padding, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	symm::encrypt_aead(&padding::pad(data, padding), key, &[], cipher).map_err(DawnCryptoError::SymmetricEncryption)
+}
+
+// decrypt data encrypted using encrypt_data_padded and remove the padding
+pub fn decrypt_data_padded(encrypted_data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	let padded = symm::decrypt_aead(encrypted_data, key, &[], cipher).map_err(DawnCryptoError::SymmetricDecryption)?;
+	match padding::unpad(&padded) {
+		Some(data) => Ok(data.to_vec()),
+		None => Err(DawnCryptoError::InvalidInput("invalid padding"))
+	}
+}
+
+// decrypt data using a symmetric key, additionally accepting the unauthenticated aes-256-cbc format of earlier versions
+// returns the data and a warning byte, which contains LEGACY_FORMAT if the legacy format was used
+pub fn decrypt_data_compat(encrypted_data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<(Vec<u8>, u8), DawnCryptoError> {
+	compat::decrypt_data(encrypted_data, key, cipher)
+}
+
+// upgrade data encrypted using the aes-256-cbc format of earlier versions to the current format
+// data already using the current format is returned unchanged
+pub fn reencrypt_legacy(encrypted_data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	compat::reencrypt(encrypted_data, key, cipher)
+}
+
+// encrypt data using a symmetric key and the selected cipher, additionally authenticating the associated data
+// the optional associated data is not encrypted, but needs to be provided unchanged for decryption
+pub fn encrypt_data_aead(data: &[u8], key: &[u8], associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	symm::encrypt_aead(data, key, associated_data.unwrap_or_default(), cipher).map_err(DawnCryptoError::SymmetricEncryption)
+}
+
+// decrypt and authenticate data using a symmetric key and the cipher it was encrypted with
+// tampered ciphertexts or mismatching associated data result in an error without returning any plaintext
+pub fn decrypt_data_aead(encrypted_data: &[u8], key: &[u8], associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	symm::decrypt_aead(encrypted_data, key, associated_data.unwrap_or_default(), cipher).map_err(DawnCryptoError::SymmetricDecryption)
+}
+
+// encrypt data deterministically (SIV-style), so the same key, associated data and data always result in the same ciphertext
+// this reveals when the same data gets encrypted twice, so only use it where that is intended
+pub fn encrypt_data_deterministic(data: &[u8], key: &[u8], associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	convergent::seal_deterministic(data, key, associated_data.unwrap_or_default(), cipher)
+}
+
+// decrypt and authenticate data encrypted using encrypt_data_deterministic
+pub fn decrypt_data_deterministic(encrypted_data: &[u8], key: &[u8], associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	convergent::open_deterministic(encrypted_data, key, associated_data.unwrap_or_default(), cipher)
+}
+
+// derive the key of convergent encryption from the content and a per-user or per-group convergence secret (e.g. from sym_key_gen)
+pub fn convergent_key(data: &[u8], convergence_secret: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	convergent::key(data, convergence_secret)
+}
+
+// encrypt data (e.g. media that gets forwarded to many chats) using convergent encryption, so a content server can deduplicate it
+// everyone using the same convergence secret gets the same ciphertext for the same data
+// returns the ciphertext and the key, which is needed for decryption
+pub fn encrypt_data_convergent(data: &[u8], convergence_secret: &[u8], cipher: SymmetricCipher) -> Result<(Vec<u8>, SecretBytes), DawnCryptoError> {
+	convergent::encrypt(data, convergence_secret, cipher)
+}
+
+// decrypt data encrypted using encrypt_data_convergent with the returned key
+pub fn decrypt_data_convergent(encrypted_data: &[u8], key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	convergent::decrypt(encrypted_data, key, cipher)
+}
+
+// derive a key from a password or PIN (e.g. a storage key) using Argon2id with a random salt
+// returns the key and the encoded parameters and salt, which need to be stored to derive the same key again
+pub fn password_key_gen(password: &[u8], params: &Argon2Params) -> Result<(SecretBytes, Vec<u8>), DawnCryptoError> {
+	password::key_gen(password, params)
+}
+
+// derive a key from a password again, using the encoded parameters and salt returned by password_key_gen
+pub fn get_password_key(password: &[u8], encoded_params: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	password::get_key(password, encoded_params)
+}
+
+// encrypt data (e.g. a local backup) using a key derived from a password, the parameters and salt are stored with the ciphertext
+pub fn encrypt_with_password(data: &[u8], password: &[u8], params: &Argon2Params, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	password::encrypt(data, password, params, cipher)
+}
+
+// decrypt data encrypted using encrypt_with_password
+pub fn decrypt_with_password(encrypted_data: &[u8], password: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+	password::decrypt(encrypted_data, password)
+}
+
+// encrypt a record (e.g. a serialized session or the keys generated by init()) for storing it on disk
+// the record id (e.g. the chat id) is authenticated, but not stored, and needs to be provided unchanged for decryption
+pub fn seal_record(record_type: RecordType, record_id: &[u8], data: &[u8], storage_key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	storage::seal(record_type, record_id, data, storage_key, cipher)
+}
+
+// decrypt a stored record, which fails if it has another type or record id
+pub fn open_record(record_type: RecordType, record_id: &[u8], sealed: &[u8], storage_key: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	storage::open(record_type, record_id, sealed, storage_key)
 }
 
-// encrypt data using a symmetric key
-pub fn encrypt_data(data: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
-	let encrypted = symm::encrypt(data, key);
-	if encrypted.is_err() { error!("symmetric encryption failed"); }
-	Ok(encrypted.unwrap())
+// sign identity material (e.g. the public keys of an init request), preferably using a composite signing key
+// identity signatures can't be passed off as message signatures or vice versa
+pub fn sign_identity(sec_key: &[u8], identity: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+	sign(sec_key, &sign::identity_signed_data(identity)).map_err(DawnCryptoError::Signing)
 }
 
-// decrypt data using a symmetric key
-pub fn decrypt_data(encrypted_data: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
-	let data = symm::decrypt(encrypted_data, key);
-	if data.is_err() { error!("symmetric decryption failed"); }
-	Ok(data.unwrap())
+// verify a signature over identity material
+pub fn verify_identity(signature: &[u8], pub_key: &[u8], identity: &[u8]) -> Result<(), DawnCryptoError> {
+	verify(signature, pub_key, &sign::identity_signed_data(identity)).map_err(DawnCryptoError::SignatureInvalid)
 }
 
 // calculates security number for given keys
 // to use it correctly, key_a needs to be the key from the party that sent the init request
-pub fn derive_security_number(key_a: &[u8], key_b: &[u8]) -> Result<String, String> {
+pub fn derive_security_number(key_a: &[u8], key_b: &[u8]) -> Result<String, DawnCryptoError> {
 	if key_a.is_empty() || key_b.is_empty() {
-		return Err("Both keys must be longer than zero bytes each".to_string())
+		return Err(DawnCryptoError::InvalidInput("both keys must be longer than zero bytes each"))
 	}
 	let mut key_a = key_a.to_vec();
 	let mut key_b = key_b.to_vec();
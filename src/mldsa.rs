@@ -0,0 +1,58 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// ML-DSA as standardized in FIPS 204, with much smaller signatures than SPHINCS+
+
+// the traits below just need to be in scope, the name doesn't matter but can't be ambigous
+use pqcrypto_traits::sign::{PublicKey as _, SecretKey as _, DetachedSignature as _};
+use crate::secret::SecretBytes;
+
+// both parameter sets share the same interface, they only differ in the module of pqcrypto-mldsa
+macro_rules! mldsa_parameter_set {
+	($name:ident) => {
+		pub mod $name {
+			use super::*;
+			use pqcrypto_mldsa::$name::*;
+			
+			pub fn keygen() -> (Vec<u8>, SecretBytes) {
+				let (pk, sk) = keypair();
+				(pk.as_bytes().to_vec(), SecretBytes::from_slice(sk.as_bytes()))
+			}
+			
+			pub fn sign(sec_key: &[u8], msg: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+				let key = SecretKey::from_bytes(sec_key)?;
+				Ok(detached_sign(msg, &key).as_bytes().to_vec())
+			}
+			
+			pub fn verify(signature: &[u8], pub_key: &[u8], msg: &[u8]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+				let key = PublicKey::from_bytes(pub_key)?;
+				let signature = DetachedSignature::from_bytes(signature)?;
+				verify_detached_signature(&signature, msg, &key)?;
+				Ok(())
+			}
+			
+			// lengths of public key, secret key and signature
+			pub fn lengths() -> (usize, usize, usize) {
+				(public_key_bytes(), secret_key_bytes(), signature_bytes())
+			}
+		}
+	};
+}
+
+mldsa_parameter_set!(mldsa65);
+mldsa_parameter_set!(mldsa87);
@@ -0,0 +1,63 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// ML-KEM as standardized in FIPS 203. It is not interoperable with the round-3 Kyber in kyber.rs.
+
+// the traits below just need to be in scope, the name doesn't matter but can't be ambigous
+use pqcrypto_traits::kem::{PublicKey as _, SecretKey as _, SharedSecret as _, Ciphertext as _};
+use crate::secret::SecretBytes;
+
+// both parameter sets share the same interface, they only differ in the module of pqcrypto-mlkem and the key sizes
+macro_rules! mlkem_parameter_set {
+	($name:ident, $k:expr) => {
+		pub mod $name {
+			use super::*;
+			use pqcrypto_mlkem::$name::*;
+			
+			pub fn keygen() -> (Vec<u8>, SecretBytes) {
+				let (pk, sk) = keypair();
+				(pk.as_bytes().to_vec(), SecretBytes::from_slice(sk.as_bytes()))
+			}
+			
+			pub fn get_shared_secret(pub_key: &[u8]) -> Result<(SecretBytes, Vec<u8>), Box<dyn std::error::Error + Send + Sync>> {
+				let pk = PublicKey::from_bytes(pub_key)?;
+				let (shared_secret, ciphertext) = encapsulate(&pk);
+				Ok((SecretBytes::from_slice(shared_secret.as_bytes()), ciphertext.as_bytes().to_vec()))
+			}
+			
+			pub fn decrypt_shared_secret(ciphertext: &[u8], sec_key: &[u8]) -> Result<SecretBytes, Box<dyn std::error::Error + Send + Sync>> {
+				let ciphertext = Ciphertext::from_bytes(ciphertext)?;
+				let sk = SecretKey::from_bytes(sec_key)?;
+				Ok(SecretBytes::from_slice(decapsulate(&ciphertext, &sk).as_bytes()))
+			}
+			
+			// the decapsulation key contains the encapsulation key after the 384*k bytes of the secret vector
+			pub fn public_key_from_secret(sec_key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+				let sk = SecretKey::from_bytes(sec_key)?;
+				Ok(sk.as_bytes()[384 * $k..384 * $k + public_key_bytes()].to_vec())
+			}
+			
+			pub fn key_lengths() -> (usize, usize) {
+				(public_key_bytes(), secret_key_bytes())
+			}
+		}
+	};
+}
+
+mlkem_parameter_set!(mlkem768, 3);
+mlkem_parameter_set!(mlkem1024, 4);
@@ -0,0 +1,80 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// length-hiding padding applied to plaintexts before encryption
+// The padded plaintext is data || 0x80 || zero bytes (as in ISO/IEC 7816-4), so the padding can be removed without
+// storing the length. Padding is only removed after the ciphertext was authenticated.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+	// only the end marker, so the length is revealed
+	None,
+	
+	// Padmé: the padded length only reveals O(log log n) bits of the length, the overhead is at most 12%
+	Padme,
+	
+	// pad to the next power of two, but never add more than max_overhead bytes
+	// beyond that, the length is rounded up to a multiple of max_overhead instead
+	PowerOfTwo { max_overhead: usize },
+}
+
+// the Padmé length as described in "Reducing Metadata Leakage from Encrypted Files and Communication with PURBs"
+fn padme(len: usize) -> usize {
+	if len < 2 { return len }
+	let exponent = usize::BITS - 1 - len.leading_zeros();
+	let exponent_bits = u32::BITS - exponent.leading_zeros();
+	let mask = (1usize << (exponent - exponent_bits)) - 1;
+	(len + mask) & !mask
+}
+
+impl Padding {
+	// length of the padded plaintext including the end marker
+	pub fn padded_len(&self, len: usize) -> usize {
+		let len = len + 1;
+		match *self {
+			Padding::None => len,
+			Padding::Padme => padme(len),
+			Padding::PowerOfTwo { max_overhead } => {
+				let bucket = len.next_power_of_two();
+				if bucket - len <= max_overhead { return bucket }
+				match max_overhead {
+					0 => len,
+					_ => len.div_ceil(max_overhead) * max_overhead
+				}
+			}
+		}
+	}
+}
+
+pub fn pad(data: &[u8], padding: Padding) -> Vec<u8> {
+	let padded_len = padding.padded_len(data.len());
+	let mut output = Vec::with_capacity(padded_len);
+	output.extend_from_slice(data);
+	output.push(0x80);
+	output.resize(padded_len, 0);
+	output
+}
+
+// remove the padding, returning None if it is invalid
+pub fn unpad(padded: &[u8]) -> Option<&[u8]> {
+	let marker = padded.iter().rposition(|byte| *byte != 0)?;
+	match padded[marker] {
+		0x80 => Some(&padded[..marker]),
+		_ => None
+	}
+}
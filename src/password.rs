@@ -0,0 +1,80 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// encryption using a key derived from a password (e.g. for local backups and keystores)
+// The Argon2id parameters and the salt are stored in front of the ciphertext, so the parameters can be raised later
+// without breaking existing files. The whole header is authenticated as associated data.
+//
+// format: version || cipher id || memory || iterations || parallelism (each u32) || salt || nonce || ciphertext || tag
+
+use rand::Rng;
+use crate::error::DawnCryptoError;
+use crate::hash::{self, Argon2Params, PASSWORD_SALT_LEN};
+use crate::secret::SecretBytes;
+use crate::symm::SymmetricCipher;
+
+pub const PASSWORD_VERSION: u8 = 1;
+const HEADER_LEN: usize = 2 + 12 + PASSWORD_SALT_LEN;
+
+// derive a key using a fresh random salt, returning the key and the encoded parameters and salt
+pub fn key_gen(password: &[u8], params: &Argon2Params) -> Result<(SecretBytes, Vec<u8>), DawnCryptoError> {
+	// we wouldn't be able to read parameters above the limits again
+	if !params.within_limits() {
+		return Err(DawnCryptoError::InvalidParameters)
+	}
+	let salt = rand::thread_rng().gen::<[u8; PASSWORD_SALT_LEN]>();
+	let key = hash::argon2id(password, &salt, params).map_err(DawnCryptoError::KeyDerivation)?;
+	Ok((key, params.encode_with_salt(&salt)))
+}
+
+// derive the key again using the encoded parameters and salt
+pub fn get_key(password: &[u8], encoded_params: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	let (params, salt) = match Argon2Params::decode_with_salt(encoded_params) {
+		Some(res) => res,
+		None => return Err(DawnCryptoError::InvalidInput("invalid password parameters"))
+	};
+	if !params.within_limits() {
+		return Err(DawnCryptoError::InvalidParameters)
+	}
+	hash::argon2id(password, salt, &params).map_err(DawnCryptoError::KeyDerivation)
+}
+
+pub fn encrypt(data: &[u8], password: &[u8], params: &Argon2Params, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	let (key, encoded_params) = key_gen(password, params)?;
+	let mut output = vec![PASSWORD_VERSION, cipher.id()];
+	output.extend_from_slice(&encoded_params);
+	let mut ciphertext = crate::encrypt_data_aead(data, &key, Some(&output), cipher)?;
+	output.append(&mut ciphertext);
+	Ok(output)
+}
+
+pub fn decrypt(encrypted_data: &[u8], password: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+	if encrypted_data.len() < HEADER_LEN {
+		return Err(DawnCryptoError::InvalidInput("encrypted data too short"))
+	}
+	if encrypted_data[0] != PASSWORD_VERSION {
+		return Err(DawnCryptoError::UnsupportedVersion(encrypted_data[0]))
+	}
+	let cipher = match SymmetricCipher::from_id(encrypted_data[1]) {
+		Some(cipher) => cipher,
+		None => return Err(DawnCryptoError::UnsupportedAlgorithm(encrypted_data[1]))
+	};
+	let (header, ciphertext) = encrypted_data.split_at(HEADER_LEN);
+	let key = get_key(password, &header[2..])?;
+	crate::decrypt_data_aead(ciphertext, &key, Some(header), cipher)
+}
@@ -0,0 +1,567 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// Double Ratchet session with post-quantum KEM steps
+// Every time the direction of the conversation changes, the sender encapsulates a fresh secret to the current ratchet key
+// of the other side using the hybrid x25519 + kyber KEM, generates a new ratchet key for the answer and mixes the secret
+// into the root key. After a compromise of the session state, the secrecy of later messages is restored with the next
+// reply turn. Within a turn, every message gets its own key from a sending chain derived with HKDF.
+// Every message carries its number within the turn and the number of messages of the previous turn of the sender, so the
+// receiver can derive the keys of lost or delayed messages in advance. These keys are kept in a bounded cache until the
+// message arrives and get deleted after use.
+// Additionally, every message carries a sequence number counting all messages the sender sent in this session. The receiver
+// keeps track of the received sequence numbers, so a message delivered twice is rejected with a replay error.
+// Messages are padded before encryption (using Padmé by default), so the ciphertext only reveals their rough length.
+//
+// message format: magic || version || cipher id || ratchet public key (length-prefixed) || kem ciphertext (length-prefixed) ||
+// number of messages in the previous turn (u32) || message number (u32) || sequence number (u64) || encrypted message (length-prefixed)
+// everything in front of the encrypted message is authenticated as associated data
+//
+// serialized state: magic || version || cipher id || session id, root key, own ratchet public key, own ratchet secret key,
+// remote ratchet key (empty if none), sending chain key (empty if none), kem ciphertext of the sending turn (each length-prefixed) ||
+// number of messages in the previous turn, message number of the sending turn, number of sent messages in the last own turn (u32 each) ||
+// sequence number (u64) || receiving chain key (empty if none), turn id of the receiving chain (each length-prefixed) ||
+// message number of the receiving chain (u32) || number of skipped keys (u32) || for every skipped key: turn id (length-prefixed) ||
+// message number (u32) || message key (length-prefixed) || next sequence number (u64) || number of missing sequence numbers (u32) ||
+// missing sequence numbers (u64 each)
+
+use std::collections::VecDeque;
+use crate::encoding::{push_field, Reader};
+use crate::error::DawnCryptoError;
+use crate::hash;
+use crate::hybrid;
+use crate::padding::{self, Padding};
+use crate::secret::SecretBytes;
+use crate::symm::{self, SymmetricCipher};
+
+pub const RATCHET_MAGIC: u8 = 0xdb;
+pub const RATCHET_VERSION: u8 = 1;
+pub const RATCHET_STATE_MAGIC: u8 = 0xde;
+pub const RATCHET_STATE_VERSION: u8 = 1;
+
+// maximum number of message keys that get derived in advance for a single message
+pub const MAX_SKIP: u32 = 1000;
+
+// maximum number of cached message keys, the oldest ones get deleted first
+pub const MAX_SKIPPED_KEYS: usize = 2000;
+
+// maximum number of missing sequence numbers that are remembered, older missing messages are treated as replays
+pub const REPLAY_WINDOW: usize = MAX_SKIPPED_KEYS;
+
+const ROOT_LABEL: &[u8] = b"dawn ratchet root key";
+const INITIAL_ROOT_LABEL: &[u8] = b"dawn ratchet initial root key";
+const CHAIN_LABEL: &[u8] = b"dawn ratchet chain key";
+const MESSAGE_KEY_LABEL: &[u8] = b"dawn ratchet message key";
+const SESSION_ID_LABEL: &[u8] = b"dawn ratchet session id";
+
+fn hkdf(ikm: &[u8], salt: &[u8], info: &[u8], len: usize) -> Result<SecretBytes, DawnCryptoError> {
+	hash::hkdf(ikm, salt, info, len).map_err(DawnCryptoError::InvalidKey)
+}
+
+// mix a fresh KEM secret into the root key, returning the new root key and chain key
+fn ratchet_root(root_key: &[u8], kem_secret: &[u8]) -> Result<(SecretBytes, SecretBytes), DawnCryptoError> {
+	let output = hkdf(kem_secret, root_key, ROOT_LABEL, 64)?;
+	Ok((SecretBytes::from_slice(&output[..32]), SecretBytes::from_slice(&output[32..])))
+}
+
+// advance a chain, returning the next chain key and the message key
+fn ratchet_chain(chain_key: &[u8]) -> Result<(SecretBytes, SecretBytes), DawnCryptoError> {
+	Ok((hkdf(chain_key, &[], CHAIN_LABEL, 32)?, hkdf(chain_key, &[], MESSAGE_KEY_LABEL, 32)?))
+}
+
+// decrypt a message and remove the padding, which is only checked after the message was authenticated
+fn decrypt_payload(payload: &[u8], message_key: &[u8], encoded_header: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	let message = symm::decrypt_aead(payload, message_key, encoded_header, cipher).map_err(DawnCryptoError::SymmetricDecryption)?;
+	match padding::unpad(&message) {
+		Some(unpadded) => Ok(unpadded.to_vec()),
+		None => Err(DawnCryptoError::InvalidMessage("invalid padding"))
+	}
+}
+
+// header of a ratchet message
+#[derive(Clone)]
+struct Header {
+	cipher: SymmetricCipher,
+	ratchet_key: Vec<u8>,
+	kem_ciphertext: Vec<u8>,
+	previous_count: u32,
+	counter: u32,
+	sequence: u64,
+}
+
+impl Header {
+	fn encode(&self) -> Vec<u8> {
+		let mut output = vec![RATCHET_MAGIC, RATCHET_VERSION, self.cipher.id()];
+		push_field(&mut output, &self.ratchet_key);
+		push_field(&mut output, &self.kem_ciphertext);
+		output.extend_from_slice(&self.previous_count.to_be_bytes());
+		output.extend_from_slice(&self.counter.to_be_bytes());
+		output.extend_from_slice(&self.sequence.to_be_bytes());
+		output
+	}
+	
+	// returns the header, its encoding and the encrypted message
+	fn parse(bytes: &[u8]) -> Result<(Header, &[u8], &[u8]), DawnCryptoError> {
+		let mut reader = Reader::new(bytes);
+		if reader.read_u8() != Some(RATCHET_MAGIC) {
+			return Err(DawnCryptoError::InvalidMessage("not a ratchet message"))
+		}
+		match reader.read_u8() {
+			Some(RATCHET_VERSION) => (),
+			Some(version) => return Err(DawnCryptoError::UnsupportedVersion(version)),
+			None => return Err(DawnCryptoError::InvalidMessage("message too short"))
+		}
+		let cipher = match reader.read_u8() {
+			Some(id) => match SymmetricCipher::from_id(id) {
+				Some(cipher) => cipher,
+				None => return Err(DawnCryptoError::UnsupportedAlgorithm(id))
+			},
+			None => return Err(DawnCryptoError::InvalidMessage("message too short"))
+		};
+		let header = match (reader.read_field(), reader.read_field(), reader.read_u32(), reader.read_u32(), reader.read_u64()) {
+			(Some(ratchet_key), Some(kem_ciphertext), Some(previous_count), Some(counter), Some(sequence)) => Header {
+				cipher,
+				ratchet_key: ratchet_key.to_vec(),
+				kem_ciphertext: kem_ciphertext.to_vec(),
+				previous_count,
+				counter,
+				sequence,
+			},
+			_ => return Err(DawnCryptoError::InvalidMessage("message truncated"))
+		};
+		let header_len = bytes.len() - reader.remaining();
+		let payload = match reader.read_field() {
+			Some(payload) => payload,
+			None => return Err(DawnCryptoError::InvalidMessage("message truncated"))
+		};
+		if !reader.is_empty() {
+			return Err(DawnCryptoError::InvalidMessage("trailing data after message"))
+		}
+		Ok((header, &bytes[..header_len], payload))
+	}
+}
+
+// chain of the current turn of the other side
+// the turn is identified by the hash of the kem ciphertext that started it
+#[derive(Clone)]
+struct ReceiveChain {
+	chain_key: SecretBytes,
+	turn_id: Vec<u8>,
+	counter: u32,
+}
+
+impl ReceiveChain {
+	// derive the keys of all messages before the given message number
+	fn skip_to(&mut self, counter: u32, skipped_keys: &mut Vec<SkippedKey>) -> Result<(), DawnCryptoError> {
+		if counter.saturating_sub(self.counter) > MAX_SKIP {
+			return Err(DawnCryptoError::InvalidMessage("too many skipped messages"))
+		}
+		while self.counter < counter {
+			let (chain_key, message_key) = ratchet_chain(&self.chain_key)?;
+			skipped_keys.push(SkippedKey { turn_id: self.turn_id.clone(), counter: self.counter, message_key });
+			self.chain_key = chain_key;
+			self.counter += 1;
+		}
+		Ok(())
+	}
+}
+
+// sequence numbers of the received messages: all numbers below next were received, except for the missing ones
+#[derive(Clone, Default)]
+struct ReplayWindow {
+	next: u64,
+	missing: VecDeque<u64>,
+}
+
+impl ReplayWindow {
+	fn is_replay(&self, sequence: u64) -> bool {
+		sequence < self.next && !self.missing.contains(&sequence)
+	}
+	
+	fn mark_received(&mut self, sequence: u64) {
+		if sequence >= self.next {
+			let start = self.next.max(sequence.saturating_sub(REPLAY_WINDOW as u64));
+			self.missing.extend(start..sequence);
+			self.next = sequence + 1;
+			while self.missing.len() > REPLAY_WINDOW {
+				self.missing.pop_front();
+			}
+		}
+		else if let Some(position) = self.missing.iter().position(|missing| *missing == sequence) {
+			self.missing.remove(position);
+		}
+	}
+}
+
+#[derive(Clone)]
+struct SkippedKey {
+	turn_id: Vec<u8>,
+	counter: u32,
+	message_key: SecretBytes,
+}
+
+#[derive(Clone)]
+pub struct Ratchet {
+	cipher: SymmetricCipher,
+	
+	// identifies the session on both sides, derived from the shared secret
+	session_id: Vec<u8>,
+	root_key: SecretBytes,
+	
+	// own ratchet keypair, the other side encapsulates to its public key when starting a turn
+	ratchet_public_key: Vec<u8>,
+	ratchet_secret_key: SecretBytes,
+	remote_ratchet_key: Option<Vec<u8>>,
+	
+	// sending chain and header of the current own turn, None if the next message starts a new turn
+	send_chain: Option<(SecretBytes, Header)>,
+	previous_send_count: u32,
+	send_sequence: u64,
+	
+	receive_chain: Option<ReceiveChain>,
+	skipped_keys: VecDeque<SkippedKey>,
+	replay_window: ReplayWindow,
+}
+
+impl Ratchet {
+	// start a session as the party knowing the ratchet public key (a hybrid public key) of the other side
+	// the shared secret needs to be known to both sides (e.g. the PFS key after init)
+	pub fn initiate(shared_secret: &[u8], remote_ratchet_key: &[u8], cipher: SymmetricCipher) -> Result<Ratchet, DawnCryptoError> {
+		let mut ratchet = Ratchet::new(shared_secret, hybrid::keygen(), cipher)?;
+		ratchet.remote_ratchet_key = Some(remote_ratchet_key.to_vec());
+		Ok(ratchet)
+	}
+	
+	// start a session as the party owning the ratchet keypair the other side used to initiate it
+	// sending is only possible after receiving the first message
+	pub fn respond(shared_secret: &[u8], ratchet_keypair: (Vec<u8>, SecretBytes), cipher: SymmetricCipher) -> Result<Ratchet, DawnCryptoError> {
+		Ratchet::new(shared_secret, ratchet_keypair, cipher)
+	}
+	
+	fn new(shared_secret: &[u8], ratchet_keypair: (Vec<u8>, SecretBytes), cipher: SymmetricCipher) -> Result<Ratchet, DawnCryptoError> {
+		if shared_secret.len() < 32 {
+			return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: shared_secret.len() })
+		}
+		let (ratchet_public_key, ratchet_secret_key) = ratchet_keypair;
+		Ok(Ratchet {
+			cipher,
+			session_id: hkdf(shared_secret, &[], SESSION_ID_LABEL, 32)?.to_vec(),
+			root_key: hkdf(shared_secret, &[], INITIAL_ROOT_LABEL, 32)?,
+			ratchet_public_key,
+			ratchet_secret_key,
+			remote_ratchet_key: None,
+			send_chain: None,
+			previous_send_count: 0,
+			send_sequence: 0,
+			receive_chain: None,
+			skipped_keys: VecDeque::new(),
+			replay_window: ReplayWindow::default(),
+		})
+	}
+	
+	pub(crate) fn session_id(&self) -> &[u8] {
+		&self.session_id
+	}
+	
+	pub fn encrypt(&mut self, msg: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+		self.encrypt_padded(msg, Padding::Padme)
+	}
+	
+	// encrypt a message using the selected padding scheme, decrypt removes any padding
+	pub fn encrypt_padded(&mut self, msg: &[u8], padding: Padding) -> Result<Vec<u8>, DawnCryptoError> {
+		
+		// start a new turn: encapsulate a secret to the ratchet key of the other side and generate a new own ratchet key
+		let (root_key, ratchet_keypair, (chain_key, mut header)) = match self.send_chain.take() {
+			Some(send_chain) => (None, None, send_chain),
+			None => {
+				let remote_ratchet_key = match &self.remote_ratchet_key {
+					Some(res) => res,
+					None => return Err(DawnCryptoError::InvalidInput("no ratchet key of the other side received yet"))
+				};
+				let (kem_secret, kem_ciphertext) = hybrid::get_shared_secret(remote_ratchet_key).map_err(DawnCryptoError::KemEncapsulation)?;
+				let (root_key, chain_key) = ratchet_root(&self.root_key, &kem_secret)?;
+				let (ratchet_public_key, ratchet_secret_key) = hybrid::keygen();
+				let header = Header {
+					cipher: self.cipher,
+					ratchet_key: ratchet_public_key.clone(),
+					kem_ciphertext,
+					previous_count: self.previous_send_count,
+					counter: 0,
+					sequence: 0,
+				};
+				(Some(root_key), Some((ratchet_public_key, ratchet_secret_key)), (chain_key, header))
+			}
+		};
+		
+		let (next_chain_key, message_key) = ratchet_chain(&chain_key)?;
+		header.sequence = self.send_sequence;
+		let encoded_header = header.encode();
+		let payload = match symm::encrypt_aead(&padding::pad(msg, padding), &message_key, &encoded_header, header.cipher) {
+			Ok(res) => res,
+			Err(err) => {
+				if root_key.is_none() { self.send_chain = Some((chain_key, header)); }
+				return Err(DawnCryptoError::SymmetricEncryption(err))
+			}
+		};
+		
+		// only update the state once the message is encrypted
+		if let Some(root_key) = root_key { self.root_key = root_key; }
+		if let Some((ratchet_public_key, ratchet_secret_key)) = ratchet_keypair {
+			self.ratchet_public_key = ratchet_public_key;
+			self.ratchet_secret_key = ratchet_secret_key;
+		}
+		let mut next_header = header;
+		next_header.counter += 1;
+		self.previous_send_count = next_header.counter;
+		self.send_chain = Some((next_chain_key, next_header));
+		self.send_sequence += 1;
+		
+		let mut output = encoded_header;
+		push_field(&mut output, &payload);
+		Ok(output)
+	}
+	
+	// every failure caused by the received message is reported as DecryptionFailed, debug::decrypt_ratchet returns the detailed error
+	pub fn decrypt(&mut self, enc_msg: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+		self.decrypt_detailed(enc_msg).map_err(crate::opaque_decryption_error)
+	}
+	
+	pub(crate) fn decrypt_detailed(&mut self, enc_msg: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+		let (header, encoded_header, payload) = Header::parse(enc_msg)?;
+		// the sequence number isn't authenticated yet, so a replay is only reported in detail by debug::decrypt_ratchet
+		if self.replay_window.is_replay(header.sequence) {
+			return Err(DawnCryptoError::Replay(header.sequence))
+		}
+		let turn_id = hash::hash(&header.kem_ciphertext);
+		
+		// delayed messages use a cached key, which gets deleted after use
+		if let Some(position) = self.skipped_keys.iter().position(|key| key.turn_id == turn_id && key.counter == header.counter) {
+			let message = decrypt_payload(payload, &self.skipped_keys[position].message_key, encoded_header, header.cipher)?;
+			self.skipped_keys.remove(position);
+			self.replay_window.mark_received(header.sequence);
+			return Ok(message)
+		}
+		
+		// work on copies, so only authentic messages change the state
+		let mut skipped_keys = vec![];
+		let (root_key, mut chain) = match &self.receive_chain {
+			Some(chain) if chain.turn_id == turn_id => (None, chain.clone()),
+			previous_chain => {
+				
+				// a new kem ciphertext means the other side started a new turn, so keep the keys of the messages
+				// of the previous turn that didn't arrive yet
+				if let Some(previous_chain) = previous_chain {
+					previous_chain.clone().skip_to(header.previous_count, &mut skipped_keys)?;
+				}
+				let kem_secret = hybrid::decrypt_shared_secret(&header.kem_ciphertext, &self.ratchet_secret_key).map_err(DawnCryptoError::KemDecapsulation)?;
+				let (root_key, chain_key) = ratchet_root(&self.root_key, &kem_secret)?;
+				(Some(root_key), ReceiveChain { chain_key, turn_id, counter: 0 })
+			}
+		};
+		if header.counter < chain.counter {
+			return Err(DawnCryptoError::InvalidMessage("message key not available"))
+		}
+		chain.skip_to(header.counter, &mut skipped_keys)?;
+		let (next_chain_key, message_key) = ratchet_chain(&chain.chain_key)?;
+		let message = decrypt_payload(payload, &message_key, encoded_header, header.cipher)?;
+		
+		// the message is authentic, so update the state
+		if let Some(root_key) = root_key {
+			self.root_key = root_key;
+			self.remote_ratchet_key = Some(header.ratchet_key);
+			
+			// the answer starts a new turn
+			self.send_chain = None;
+		}
+		chain.chain_key = next_chain_key;
+		chain.counter += 1;
+		self.receive_chain = Some(chain);
+		self.skipped_keys.extend(skipped_keys);
+		while self.skipped_keys.len() > MAX_SKIPPED_KEYS {
+			self.skipped_keys.pop_front();
+		}
+		self.replay_window.mark_received(header.sequence);
+		Ok(message)
+	}
+	
+	// serialize the state of the ratchet, the output contains secret keys and needs to be stored securely
+	pub fn to_bytes(&self) -> SecretBytes {
+		let empty: &[u8] = &[];
+		let (send_chain_key, send_header) = match &self.send_chain {
+			Some((chain_key, header)) => (&chain_key[..], Some(header)),
+			None => (empty, None)
+		};
+		let (receive_chain_key, receive_turn_id, receive_counter) = match &self.receive_chain {
+			Some(chain) => (&chain.chain_key[..], &chain.turn_id[..], chain.counter),
+			None => (empty, empty, 0)
+		};
+		let fields = [
+			&self.session_id[..],
+			&self.root_key,
+			&self.ratchet_public_key,
+			&self.ratchet_secret_key,
+			self.remote_ratchet_key.as_deref().unwrap_or(empty),
+			send_chain_key,
+			send_header.map_or(empty, |header| &header.kem_ciphertext),
+		];
+		
+		// allocate everything at once, so no copies of the secrets are left behind when the buffer grows
+		let len = 3 + fields.iter().map(|field| 4 + field.len()).sum::<usize>() + 20
+			+ 12 + receive_chain_key.len() + receive_turn_id.len()
+			+ 4 + self.skipped_keys.iter().map(|key| 12 + key.turn_id.len() + key.message_key.len()).sum::<usize>()
+			+ 12 + 8 * self.replay_window.missing.len();
+		let mut output = Vec::with_capacity(len);
+		output.extend_from_slice(&[RATCHET_STATE_MAGIC, RATCHET_STATE_VERSION, self.cipher.id()]);
+		for field in fields {
+			push_field(&mut output, field);
+		}
+		output.extend_from_slice(&send_header.map_or(0, |header| header.previous_count).to_be_bytes());
+		output.extend_from_slice(&send_header.map_or(0, |header| header.counter).to_be_bytes());
+		output.extend_from_slice(&self.previous_send_count.to_be_bytes());
+		output.extend_from_slice(&self.send_sequence.to_be_bytes());
+		push_field(&mut output, receive_chain_key);
+		push_field(&mut output, receive_turn_id);
+		output.extend_from_slice(&receive_counter.to_be_bytes());
+		output.extend_from_slice(&(self.skipped_keys.len() as u32).to_be_bytes());
+		for key in &self.skipped_keys {
+			push_field(&mut output, &key.turn_id);
+			output.extend_from_slice(&key.counter.to_be_bytes());
+			push_field(&mut output, &key.message_key);
+		}
+		output.extend_from_slice(&self.replay_window.next.to_be_bytes());
+		output.extend_from_slice(&(self.replay_window.missing.len() as u32).to_be_bytes());
+		for sequence in &self.replay_window.missing {
+			output.extend_from_slice(&sequence.to_be_bytes());
+		}
+		SecretBytes::from(output)
+	}
+	
+	// restore a serialized ratchet, validating the keys
+	pub fn from_bytes(bytes: &[u8]) -> Result<Ratchet, DawnCryptoError> {
+		let truncated = DawnCryptoError::InvalidInput("serialized ratchet truncated");
+		let mut reader = Reader::new(bytes);
+		if reader.read_u8() != Some(RATCHET_STATE_MAGIC) {
+			return Err(DawnCryptoError::InvalidInput("not a serialized ratchet"))
+		}
+		match reader.read_u8() {
+			Some(RATCHET_STATE_VERSION) => (),
+			Some(version) => return Err(DawnCryptoError::UnsupportedVersion(version)),
+			None => return Err(truncated)
+		}
+		let cipher = match reader.read_u8() {
+			Some(id) => match SymmetricCipher::from_id(id) {
+				Some(cipher) => cipher,
+				None => return Err(DawnCryptoError::UnsupportedAlgorithm(id))
+			},
+			None => return Err(truncated)
+		};
+		let mut fields = vec![];
+		for _ in 0..7 {
+			match reader.read_field() {
+				Some(field) => fields.push(field),
+				None => return Err(truncated)
+			}
+		}
+		let (send_previous_count, send_counter, previous_send_count, send_sequence) = match (reader.read_u32(), reader.read_u32(), reader.read_u32(), reader.read_u64()) {
+			(Some(send_previous_count), Some(send_counter), Some(previous_send_count), Some(send_sequence)) => (send_previous_count, send_counter, previous_send_count, send_sequence),
+			_ => return Err(truncated)
+		};
+		let (receive_chain_key, receive_turn_id, receive_counter) = match (reader.read_field(), reader.read_field(), reader.read_u32()) {
+			(Some(receive_chain_key), Some(receive_turn_id), Some(receive_counter)) => (receive_chain_key, receive_turn_id, receive_counter),
+			_ => return Err(truncated)
+		};
+		let skipped_count = match reader.read_u32() {
+			Some(count) if count as usize <= MAX_SKIPPED_KEYS => count,
+			Some(_) => return Err(DawnCryptoError::InvalidInput("too many skipped keys")),
+			None => return Err(truncated)
+		};
+		let mut skipped_keys = VecDeque::new();
+		for _ in 0..skipped_count {
+			match (reader.read_field(), reader.read_u32(), reader.read_field()) {
+				(Some(turn_id), Some(counter), Some(message_key)) if message_key.len() == 32 => skipped_keys.push_back(SkippedKey {
+					turn_id: turn_id.to_vec(),
+					counter,
+					message_key: SecretBytes::from_slice(message_key),
+				}),
+				(Some(_), Some(_), Some(_)) => return Err(DawnCryptoError::InvalidInput("invalid skipped key")),
+				_ => return Err(truncated)
+			}
+		}
+		let (next_sequence, missing_count) = match (reader.read_u64(), reader.read_u32()) {
+			(Some(next_sequence), Some(count)) if count as usize <= REPLAY_WINDOW => (next_sequence, count),
+			(Some(_), Some(_)) => return Err(DawnCryptoError::InvalidInput("too many missing sequence numbers")),
+			_ => return Err(truncated)
+		};
+		let mut missing = VecDeque::new();
+		for _ in 0..missing_count {
+			match reader.read_u64() {
+				Some(sequence) => missing.push_back(sequence),
+				None => return Err(truncated)
+			}
+		}
+		if !reader.is_empty() {
+			return Err(DawnCryptoError::InvalidInput("trailing data after serialized ratchet"))
+		}
+		
+		// the keys need to have the right length and the ratchet keypair needs to match
+		if fields[0].len() != 32 || fields[1].len() != 32 || !(fields[5].is_empty() || fields[5].len() == 32) || !(receive_chain_key.is_empty() || receive_chain_key.len() == 32) {
+			return Err(DawnCryptoError::InvalidInput("invalid key in serialized ratchet"))
+		}
+		match hybrid::public_key_from_secret(fields[3]) {
+			Ok(public_key) if public_key == fields[2] => (),
+			_ => return Err(DawnCryptoError::InvalidInput("invalid ratchet keypair in serialized ratchet"))
+		}
+		
+		let send_chain = match fields[5].is_empty() {
+			true => None,
+			false => Some((SecretBytes::from_slice(fields[5]), Header {
+				cipher,
+				ratchet_key: fields[2].to_vec(),
+				kem_ciphertext: fields[6].to_vec(),
+				previous_count: send_previous_count,
+				counter: send_counter,
+				sequence: 0,
+			}))
+		};
+		let receive_chain = match receive_chain_key.is_empty() {
+			true => None,
+			false => Some(ReceiveChain {
+				chain_key: SecretBytes::from_slice(receive_chain_key),
+				turn_id: receive_turn_id.to_vec(),
+				counter: receive_counter,
+			})
+		};
+		Ok(Ratchet {
+			cipher,
+			session_id: fields[0].to_vec(),
+			root_key: SecretBytes::from_slice(fields[1]),
+			ratchet_public_key: fields[2].to_vec(),
+			ratchet_secret_key: SecretBytes::from_slice(fields[3]),
+			remote_ratchet_key: match fields[4].is_empty() {
+				true => None,
+				false => Some(fields[4].to_vec())
+			},
+			send_chain,
+			previous_send_count,
+			send_sequence,
+			receive_chain,
+			skipped_keys,
+			replay_window: ReplayWindow { next: next_sequence, missing },
+		})
+	}
+}
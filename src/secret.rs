@@ -0,0 +1,89 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// container for secret bytes (keys, shared secrets and intermediate values derived from them)
+// the memory gets wiped when it is dropped and the content is never printed by Debug
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+#[derive(Clone, Default)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+	pub fn from_slice(bytes: &[u8]) -> SecretBytes {
+		SecretBytes(bytes.to_vec())
+	}
+	
+	// concatenate the parts into a buffer that is allocated once, so growing it doesn't leave copies in freed memory
+	pub fn concat(parts: &[&[u8]]) -> SecretBytes {
+		let mut bytes = Vec::with_capacity(parts.iter().map(|part| part.len()).sum());
+		for part in parts {
+			bytes.extend_from_slice(part);
+		}
+		SecretBytes(bytes)
+	}
+}
+
+impl From<Vec<u8>> for SecretBytes {
+	fn from(bytes: Vec<u8>) -> SecretBytes {
+		SecretBytes(bytes)
+	}
+}
+
+impl Deref for SecretBytes {
+	type Target = [u8];
+	
+	fn deref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl DerefMut for SecretBytes {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		&mut self.0
+	}
+}
+
+impl AsRef<[u8]> for SecretBytes {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+// comparison in constant time, so comparing secrets doesn't leak where they differ
+impl PartialEq for SecretBytes {
+	fn eq(&self, other: &SecretBytes) -> bool {
+		self.0.len() == other.0.len() && openssl::memcmp::eq(&self.0, &other.0)
+	}
+}
+
+impl Eq for SecretBytes {}
+
+impl fmt::Debug for SecretBytes {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "SecretBytes(<{} bytes redacted>)", self.0.len())
+	}
+}
+
+impl Drop for SecretBytes {
+	fn drop(&mut self) {
+		self.0.zeroize();
+	}
+}
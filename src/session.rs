@@ -0,0 +1,231 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// all cryptographic state of a chat in one place
+// The session owns the Double Ratchet encrypting the messages of the chat, the signing keys of both sides, the salts used
+// to advance the ID seeds, the ID seeds and the MDC seed. Callers only need to persist the session after every message.
+// The ID seeds are advanced once for every 4-hour period, so both sides derive the same temporary IDs no matter when they rotate.
+// The ID seeds and MDCs are derived using the HKDF key schedule.
+// Signatures cover the session id of the ratchet and the message, so a signed message can't be moved into another session.
+//
+// serialized format: magic || version || the following fields, each length-prefixed:
+// serialized ratchet, own signing key (empty if none), remote verifying key (empty if none), sending salt, receiving salt,
+// own id seed, remote id seed, mdc seed, id timestamp
+
+use hex::encode;
+use crate::encoding::{push_field, Reader};
+use crate::envelope;
+use crate::error::DawnCryptoError;
+use crate::id;
+use crate::keys::{SigningKey, VerifyingKey, Salt};
+use crate::ratchet::Ratchet;
+use crate::secret::SecretBytes;
+use crate::sign;
+use crate::warning::*;
+
+pub const SESSION_MAGIC: u8 = 0xdc;
+pub const SESSION_VERSION: u8 = 1;
+
+const SIGNATURE_LABEL: &[u8] = b"dawn-crypto signed session message v1";
+
+fn signed_data(session_id: &[u8], msg: &[u8]) -> Vec<u8> {
+	let mut output = vec![];
+	push_field(&mut output, SIGNATURE_LABEL);
+	push_field(&mut output, session_id);
+	push_field(&mut output, msg);
+	output
+}
+
+pub struct Session {
+	ratchet: Ratchet,
+	signing_key: Option<SigningKey>,
+	remote_verifying_key: Option<VerifyingKey>,
+	
+	// the id seeds are advanced using the salts, the other side uses our sending salt for receiving and vice-versa
+	send_salt: Salt,
+	receive_salt: Salt,
+	
+	// own id seed for receiving messages, remote id seed for sending them
+	id_seed: String,
+	remote_id_seed: String,
+	mdc_seed: String,
+	
+	// timestamp of the period the id seeds belong to
+	id_timestamp: String,
+}
+
+impl Session {
+	// create a session from a ratchet and the values exchanged when initializing the chat
+	// salts and id_seeds are given as (sending, receiving) and (own, remote) respectively, so the other side creates its
+	// session with the same values swapped. Both sides need to pass the same timestamp (e.g. the one of the init request).
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(ratchet: Ratchet, signing_key: Option<SigningKey>, remote_verifying_key: Option<VerifyingKey>, salts: (Salt, Salt), id_seeds: (&str, &str), mdc_seed: &str, init_timestamp: &str) -> Result<Session, DawnCryptoError> {
+		if !id::is_id_seed(id_seeds.0) || !id::is_id_seed(id_seeds.1) {
+			return Err(DawnCryptoError::InvalidId)
+		}
+		id::parse_timestamp(init_timestamp)?;
+		Ok(Session {
+			ratchet,
+			signing_key,
+			remote_verifying_key,
+			send_salt: salts.0,
+			receive_salt: salts.1,
+			id_seed: id_seeds.0.to_string(),
+			remote_id_seed: id_seeds.1.to_string(),
+			mdc_seed: mdc_seed.to_string(),
+			id_timestamp: init_timestamp.to_string(),
+		})
+	}
+	
+	// encrypt (and sign, if there is a signing key) a message for the other side, advancing the ratchet
+	pub fn encrypt(&mut self, msg: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+		let signature = match &self.signing_key {
+			Some(signing_key) => sign::sign(signing_key.as_bytes(), &signed_data(self.ratchet.session_id(), msg)).map_err(DawnCryptoError::Signing)?,
+			None => vec![]
+		};
+		self.ratchet.encrypt(&envelope::encode_payload(&signature, msg))
+	}
+	
+	// decrypt a message of the other side (checking the signature, if there is a verifying key), advancing the ratchet
+	// returns the message and a warning byte like decrypt_msg
+	// every failure caused by the received message is reported as DecryptionFailed, debug::decrypt_session returns the detailed error
+	pub fn decrypt(&mut self, enc_msg: &[u8]) -> Result<(Vec<u8>, u8), DawnCryptoError> {
+		self.decrypt_detailed(enc_msg).map_err(crate::opaque_decryption_error)
+	}
+	
+	pub(crate) fn decrypt_detailed(&mut self, enc_msg: &[u8]) -> Result<(Vec<u8>, u8), DawnCryptoError> {
+		
+		// check the signature on a copy of the ratchet, so a message with an invalid signature doesn't change the state
+		let mut ratchet = self.ratchet.clone();
+		let payload = ratchet.decrypt_detailed(enc_msg)?;
+		let (signature, msg) = envelope::decode_payload(&payload)?;
+		let mut warning = 0u8;
+		if signature.is_empty() {
+			warning += NO_SIGNATURE;
+		}
+		else if let Some(remote_verifying_key) = &self.remote_verifying_key {
+			sign::verify(&signature, remote_verifying_key.as_bytes(), &signed_data(ratchet.session_id(), &msg)).map_err(DawnCryptoError::SignatureInvalid)?;
+		}
+		self.ratchet = ratchet;
+		Ok((msg, warning))
+	}
+	
+	// the temporary id to receive messages with in the current period, call rotate() first
+	pub fn current_temp_id(&self) -> Result<String, DawnCryptoError> {
+		id::get_custom_temp_id(&self.id_seed, &self.id_timestamp)
+	}
+	
+	// the temporary id to send messages to in the current period, call rotate() first
+	pub fn remote_temp_id(&self) -> Result<String, DawnCryptoError> {
+		id::get_custom_temp_id(&self.remote_id_seed, &self.id_timestamp)
+	}
+	
+	// the message detail code for a message sent using the given temporary id
+	pub fn mdc(&self, temp_id: &str) -> Result<String, DawnCryptoError> {
+		crate::predictable_mdc_gen(&self.mdc_seed, temp_id)
+	}
+	
+	pub fn id_timestamp(&self) -> &str {
+		&self.id_timestamp
+	}
+	
+	// advance both id seeds once for every period since the last rotation
+	pub fn rotate(&mut self) -> Result<(), DawnCryptoError> {
+		let timestamps = id::get_all_timestamps_since(&self.id_timestamp)?;
+		let mut id_seed = self.id_seed.clone();
+		let mut remote_id_seed = self.remote_id_seed.clone();
+		for _ in 1..timestamps.len() {
+			id_seed = id::get_next(&id_seed, &encode(self.receive_salt.as_bytes()))?;
+			remote_id_seed = id::get_next(&remote_id_seed, &encode(self.send_salt.as_bytes()))?;
+		}
+		self.id_seed = id_seed;
+		self.remote_id_seed = remote_id_seed;
+		self.id_timestamp = timestamps[timestamps.len() - 1].clone();
+		Ok(())
+	}
+	
+	// serialize the session, the output contains secret keys and needs to be stored securely
+	pub fn to_bytes(&self) -> SecretBytes {
+		let empty: &[u8] = &[];
+		let ratchet = self.ratchet.to_bytes();
+		let fields = [
+			&ratchet[..],
+			self.signing_key.as_ref().map_or(empty, |key| key.as_bytes()),
+			self.remote_verifying_key.as_ref().map_or(empty, |key| key.as_bytes()),
+			self.send_salt.as_bytes(),
+			self.receive_salt.as_bytes(),
+			self.id_seed.as_bytes(),
+			self.remote_id_seed.as_bytes(),
+			self.mdc_seed.as_bytes(),
+			self.id_timestamp.as_bytes(),
+		];
+		
+		// allocate everything at once, so no copies of the secrets are left behind when the buffer grows
+		let mut output = Vec::with_capacity(2 + fields.iter().map(|field| 4 + field.len()).sum::<usize>());
+		output.extend_from_slice(&[SESSION_MAGIC, SESSION_VERSION]);
+		for field in fields {
+			push_field(&mut output, field);
+		}
+		SecretBytes::from(output)
+	}
+	
+	// restore a serialized session, validating all keys
+	pub fn from_bytes(bytes: &[u8]) -> Result<Session, DawnCryptoError> {
+		let mut reader = Reader::new(bytes);
+		if reader.read_u8() != Some(SESSION_MAGIC) {
+			return Err(DawnCryptoError::InvalidInput("not a serialized session"))
+		}
+		match reader.read_u8() {
+			Some(SESSION_VERSION) => (),
+			Some(version) => return Err(DawnCryptoError::UnsupportedVersion(version)),
+			None => return Err(DawnCryptoError::InvalidInput("serialized session truncated"))
+		}
+		let mut fields = vec![];
+		for _ in 0..9 {
+			match reader.read_field() {
+				Some(field) => fields.push(field),
+				None => return Err(DawnCryptoError::InvalidInput("serialized session truncated"))
+			}
+		}
+		if !reader.is_empty() {
+			return Err(DawnCryptoError::InvalidInput("trailing data after serialized session"))
+		}
+		
+		let signing_key = match fields[1].is_empty() {
+			true => None,
+			false => Some(SigningKey::from_bytes(fields[1])?)
+		};
+		let remote_verifying_key = match fields[2].is_empty() {
+			true => None,
+			false => Some(VerifyingKey::from_bytes(fields[2])?)
+		};
+		let (id_seed, remote_id_seed, mdc_seed, id_timestamp) = match (std::str::from_utf8(fields[5]), std::str::from_utf8(fields[6]), std::str::from_utf8(fields[7]), std::str::from_utf8(fields[8])) {
+			(Ok(id_seed), Ok(remote_id_seed), Ok(mdc_seed), Ok(id_timestamp)) => (id_seed, remote_id_seed, mdc_seed, id_timestamp),
+			_ => return Err(DawnCryptoError::InvalidInput("serialized session contains invalid text"))
+		};
+		Session::new(
+			Ratchet::from_bytes(fields[0])?,
+			signing_key,
+			remote_verifying_key,
+			(Salt::from_bytes(fields[3])?, Salt::from_bytes(fields[4])?),
+			(id_seed, remote_id_seed),
+			mdc_seed,
+			id_timestamp
+		)
+	}
+}
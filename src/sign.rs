@@ -16,42 +16,216 @@
 	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
 */
 
-// the traits below just need to be in scope, the name doesn't matter but can't be ambigous
-use pqcrypto_traits::sign::{PublicKey as _, SecretKey as _, DetachedSignature as _};
-use pqcrypto_sphincsplus::sphincsshake192fsimple::{
-	detached_sign,
-	verify_detached_signature,
-	keypair,
+// selection of the signature algorithm
+// keys and signatures get encoded as algorithm id || raw key or signature. SPHINCS+ keys created before the algorithm
+// could be selected carry no id and produce signatures without id, they are recognized by their length.
+// Composite algorithms combine Ed25519 with a post-quantum algorithm, their keys and signatures consist of the
+// Ed25519 part followed by the post-quantum part. A composite signature is only valid if both parts verify.
+
+use crate::ed25519;
+use crate::encoding::push_field;
+use crate::error::DawnCryptoError;
+use crate::mldsa::{mldsa65, mldsa87};
+use crate::secret::SecretBytes;
+use crate::sphincs;
+
+type SignResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+// both parts of a composite signature sign the message prefixed with this label and the algorithm id,
+// so a part can't be taken out and passed off as a signature of the post-quantum algorithm alone
+const COMPOSITE_LABEL: &[u8] = b"dawn-crypto composite signature";
+
+const IDENTITY_LABEL: &[u8] = b"dawn-crypto signed identity v1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+	// SPHINCS+-SHAKE-192f-simple, relying only on hash functions
+	SphincsShake192f,
+	MlDsa65,
+	MlDsa87,
+	Ed25519MlDsa65,
+	Ed25519MlDsa87,
+	Ed25519SphincsShake192f,
+}
+
+impl SignatureAlgorithm {
+	pub fn id(self) -> u8 {
+		match self {
+			SignatureAlgorithm::SphincsShake192f => 1,
+			SignatureAlgorithm::MlDsa65 => 2,
+			SignatureAlgorithm::MlDsa87 => 3,
+			SignatureAlgorithm::Ed25519MlDsa65 => 4,
+			SignatureAlgorithm::Ed25519MlDsa87 => 5,
+			SignatureAlgorithm::Ed25519SphincsShake192f => 6,
+		}
+	}
+	
+	pub fn from_id(id: u8) -> Option<SignatureAlgorithm> {
+		match id {
+			1 => Some(SignatureAlgorithm::SphincsShake192f),
+			2 => Some(SignatureAlgorithm::MlDsa65),
+			3 => Some(SignatureAlgorithm::MlDsa87),
+			4 => Some(SignatureAlgorithm::Ed25519MlDsa65),
+			5 => Some(SignatureAlgorithm::Ed25519MlDsa87),
+			6 => Some(SignatureAlgorithm::Ed25519SphincsShake192f),
+			_ => None
+		}
+	}
+	
+	// the post-quantum part of a composite algorithm
+	fn post_quantum_part(self) -> Option<SignatureAlgorithm> {
+		match self {
+			SignatureAlgorithm::Ed25519MlDsa65 => Some(SignatureAlgorithm::MlDsa65),
+			SignatureAlgorithm::Ed25519MlDsa87 => Some(SignatureAlgorithm::MlDsa87),
+			SignatureAlgorithm::Ed25519SphincsShake192f => Some(SignatureAlgorithm::SphincsShake192f),
+			_ => None
+		}
+	}
+	
+	// lengths of the raw public key, secret key and signature
+	fn lengths(self) -> (usize, usize, usize) {
+		match self {
+			SignatureAlgorithm::SphincsShake192f => sphincs::lengths(),
+			SignatureAlgorithm::MlDsa65 => mldsa65::lengths(),
+			SignatureAlgorithm::MlDsa87 => mldsa87::lengths(),
+			composite => {
+				let (public_key_len, secret_key_len, signature_len) = composite.post_quantum_part().unwrap().lengths();
+				let (ed_public_key_len, ed_secret_key_len, ed_signature_len) = ed25519::lengths();
+				(ed_public_key_len + public_key_len, ed_secret_key_len + secret_key_len, ed_signature_len + signature_len)
+			}
+		}
+	}
+}
+
+// the parts of an encoding
+#[derive(Clone, Copy)]
+enum Part {
 	PublicKey,
 	SecretKey,
-	DetachedSignature
-};
+	Signature,
+}
 
-// generate a keypair
-pub fn keygen() -> (Vec<u8>, Vec<u8>) {
-	
-	let (pk, sk) = keypair();
-	let public_key = pk.as_bytes().to_vec();
-	let secret_key = sk.as_bytes().to_vec();
-	(public_key, secret_key)
+// split an encoded key or signature into its algorithm, the raw bytes and whether it was tagged
+fn decode(encoded: &[u8], part: Part) -> Result<(SignatureAlgorithm, &[u8], bool), DawnCryptoError> {
+	let select = |(public_key_len, secret_key_len, signature_len): (usize, usize, usize)| match part {
+		Part::PublicKey => public_key_len,
+		Part::SecretKey => secret_key_len,
+		Part::Signature => signature_len,
+	};
+	let legacy_len = select(sphincs::lengths());
+	if encoded.len() == legacy_len { return Ok((SignatureAlgorithm::SphincsShake192f, encoded, false)) }
+	let (id, raw) = match encoded.split_first() {
+		Some(res) => res,
+		None => return Err(DawnCryptoError::InvalidKeyLength { expected: legacy_len, actual: 0 })
+	};
+	let algorithm = match SignatureAlgorithm::from_id(*id) {
+		Some(res) => res,
+		None => return Err(DawnCryptoError::UnsupportedAlgorithm(*id))
+	};
+	let expected = select(algorithm.lengths());
+	if raw.len() != expected {
+		return Err(DawnCryptoError::InvalidKeyLength { expected: expected + 1, actual: encoded.len() })
+	}
+	Ok((algorithm, raw, true))
 }
 
-// sign a message
-pub fn sign(sec_key: &[u8], msg: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+fn tag(algorithm: SignatureAlgorithm, raw: &[u8]) -> Vec<u8> {
+	[&[algorithm.id()], raw].concat()
+}
 
-	let key = SecretKey::from_bytes(sec_key)?;
-	let signature = detached_sign(msg.as_bytes(), &key)
-		.as_bytes()
-		.to_vec();
-	
-	Ok(signature)
+// get the algorithm of an encoded public or secret key
+pub fn key_algorithm(key: &[u8]) -> Result<SignatureAlgorithm, DawnCryptoError> {
+	match public_key_algorithm(key) {
+		Ok(algorithm) => Ok(algorithm),
+		Err(_) => secret_key_algorithm(key)
+	}
+}
+
+pub fn public_key_algorithm(pub_key: &[u8]) -> Result<SignatureAlgorithm, DawnCryptoError> {
+	Ok(decode(pub_key, Part::PublicKey)?.0)
+}
+
+pub fn secret_key_algorithm(sec_key: &[u8]) -> Result<SignatureAlgorithm, DawnCryptoError> {
+	Ok(decode(sec_key, Part::SecretKey)?.0)
+}
+
+// generate a tagged keypair
+pub fn keygen(algorithm: SignatureAlgorithm) -> (Vec<u8>, SecretBytes) {
+	let (pk, sk) = keygen_raw(algorithm);
+	(tag(algorithm, &pk), SecretBytes::concat(&[&[algorithm.id()], &sk]))
+}
+
+fn keygen_raw(algorithm: SignatureAlgorithm) -> (Vec<u8>, SecretBytes) {
+	match algorithm {
+		SignatureAlgorithm::SphincsShake192f => sphincs::keygen(),
+		SignatureAlgorithm::MlDsa65 => mldsa65::keygen(),
+		SignatureAlgorithm::MlDsa87 => mldsa87::keygen(),
+		composite => {
+			let (mut pk, sk) = ed25519::keygen();
+			let (pq_pk, pq_sk) = keygen_raw(composite.post_quantum_part().unwrap());
+			pk.extend_from_slice(&pq_pk);
+			(pk, SecretBytes::concat(&[&sk, &pq_sk]))
+		}
+	}
+}
+
+// sign a message, the signature is tagged if the key is
+pub fn sign(sec_key: &[u8], msg: &[u8]) -> SignResult<Vec<u8>> {
+	let (algorithm, sec_key, tagged) = decode(sec_key, Part::SecretKey)?;
+	let signature = sign_raw(algorithm, sec_key, msg)?;
+	match tagged {
+		true => Ok(tag(algorithm, &signature)),
+		false => Ok(signature)
+	}
+}
+
+fn sign_raw(algorithm: SignatureAlgorithm, sec_key: &[u8], msg: &[u8]) -> SignResult<Vec<u8>> {
+	match algorithm {
+		SignatureAlgorithm::SphincsShake192f => sphincs::sign(sec_key, msg),
+		SignatureAlgorithm::MlDsa65 => mldsa65::sign(sec_key, msg),
+		SignatureAlgorithm::MlDsa87 => mldsa87::sign(sec_key, msg),
+		composite => {
+			let msg = [COMPOSITE_LABEL, &[composite.id()], msg].concat();
+			let (ed_sec_key, pq_sec_key) = sec_key.split_at(ed25519::lengths().1);
+			let mut signature = ed25519::sign(ed_sec_key, &msg)?;
+			signature.append(&mut sign_raw(composite.post_quantum_part().unwrap(), pq_sec_key, &msg)?);
+			Ok(signature)
+		}
+	}
 }
 
 // verify a signature
-pub fn verify(signature: &[u8], pub_key: &[u8], msg: &str) -> Result<(), Box<dyn std::error::Error>> {
-	
-	let key = PublicKey::from_bytes(pub_key)?;
-	let signature = DetachedSignature::from_bytes(signature)?;
-	verify_detached_signature(&signature, msg.as_bytes(), &key)?;
-	Ok(())
+pub fn verify(signature: &[u8], pub_key: &[u8], msg: &[u8]) -> SignResult<()> {
+	let (algorithm, pub_key, _) = decode(pub_key, Part::PublicKey)?;
+	let (signature_algorithm, signature, _) = decode(signature, Part::Signature)?;
+	if algorithm != signature_algorithm {
+		return Err(Box::new(DawnCryptoError::UnsupportedAlgorithm(signature_algorithm.id())))
+	}
+	verify_raw(algorithm, signature, pub_key, msg)
+}
+
+fn verify_raw(algorithm: SignatureAlgorithm, signature: &[u8], pub_key: &[u8], msg: &[u8]) -> SignResult<()> {
+	match algorithm {
+		SignatureAlgorithm::SphincsShake192f => sphincs::verify(signature, pub_key, msg),
+		SignatureAlgorithm::MlDsa65 => mldsa65::verify(signature, pub_key, msg),
+		SignatureAlgorithm::MlDsa87 => mldsa87::verify(signature, pub_key, msg),
+		composite => {
+			let msg = [COMPOSITE_LABEL, &[composite.id()], msg].concat();
+			let (ed_public_key_len, _, ed_signature_len) = ed25519::lengths();
+			let (ed_pub_key, pq_pub_key) = pub_key.split_at(ed_public_key_len);
+			let (ed_signature, pq_signature) = signature.split_at(ed_signature_len);
+			
+			// both parts need to be valid
+			ed25519::verify(ed_signature, ed_pub_key, &msg)?;
+			verify_raw(composite.post_quantum_part().unwrap(), pq_signature, pq_pub_key, &msg)
+		}
+	}
+}
+
+// the data signed for identity material, framed differently than the data signed for messages
+pub fn identity_signed_data(identity: &[u8]) -> Vec<u8> {
+	let mut output = vec![];
+	push_field(&mut output, IDENTITY_LABEL);
+	push_field(&mut output, identity);
+	output
 }
@@ -0,0 +1,108 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// sealed storage for data that clients keep on disk, like serialized sessions or the keys generated by init()
+// Records are encrypted under a storage key using an AEAD cipher. The header and an identifier chosen by the caller
+// (e.g. the chat or file the record belongs to) are authenticated as associated data, so a record can't be swapped
+// for a record of another type or another identifier without decryption failing.
+//
+// format: magic || version || record type || cipher id || nonce || ciphertext || tag
+
+use crate::encoding::{push_field, Reader};
+use crate::error::DawnCryptoError;
+use crate::secret::SecretBytes;
+use crate::session::Session;
+use crate::symm::{self, SymmetricCipher};
+
+pub const STORAGE_MAGIC: u8 = 0xdd;
+pub const STORAGE_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordType {
+	Session,
+	Identity,
+}
+
+impl RecordType {
+	pub fn id(&self) -> u8 {
+		match self {
+			RecordType::Session => 1,
+			RecordType::Identity => 2,
+		}
+	}
+	
+	pub fn from_id(id: u8) -> Option<RecordType> {
+		match id {
+			1 => Some(RecordType::Session),
+			2 => Some(RecordType::Identity),
+			_ => None
+		}
+	}
+}
+
+fn associated_data(header: &[u8], record_id: &[u8]) -> Vec<u8> {
+	let mut output = header.to_vec();
+	push_field(&mut output, record_id);
+	output
+}
+
+// encrypt a record under the storage key
+pub fn seal(record_type: RecordType, record_id: &[u8], data: &[u8], storage_key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	if storage_key.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: storage_key.len() }) }
+	let mut output = vec![STORAGE_MAGIC, STORAGE_VERSION, record_type.id(), cipher.id()];
+	let mut ciphertext = symm::encrypt_aead(data, storage_key, &associated_data(&output, record_id), cipher).map_err(DawnCryptoError::SymmetricEncryption)?;
+	output.append(&mut ciphertext);
+	Ok(output)
+}
+
+// decrypt a record of the expected type and identifier
+pub fn open(record_type: RecordType, record_id: &[u8], sealed: &[u8], storage_key: &[u8]) -> Result<SecretBytes, DawnCryptoError> {
+	if storage_key.len() != 32 { return Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: storage_key.len() }) }
+	let mut reader = Reader::new(sealed);
+	if reader.read_u8() != Some(STORAGE_MAGIC) {
+		return Err(DawnCryptoError::InvalidInput("not a sealed record"))
+	}
+	match reader.read_u8() {
+		Some(STORAGE_VERSION) => (),
+		Some(version) => return Err(DawnCryptoError::UnsupportedVersion(version)),
+		None => return Err(DawnCryptoError::InvalidInput("sealed record truncated"))
+	}
+	match reader.read_u8().map(RecordType::from_id) {
+		Some(Some(res)) if res == record_type => (),
+		Some(_) => return Err(DawnCryptoError::InvalidInput("sealed record has another type")),
+		None => return Err(DawnCryptoError::InvalidInput("sealed record truncated"))
+	}
+	let cipher = match reader.read_u8() {
+		Some(id) => match SymmetricCipher::from_id(id) {
+			Some(cipher) => cipher,
+			None => return Err(DawnCryptoError::UnsupportedAlgorithm(id))
+		},
+		None => return Err(DawnCryptoError::InvalidInput("sealed record truncated"))
+	};
+	let (header, ciphertext) = sealed.split_at(4);
+	let data = symm::decrypt_aead(ciphertext, storage_key, &associated_data(header, record_id), cipher).map_err(DawnCryptoError::SymmetricDecryption)?;
+	Ok(SecretBytes::from(data))
+}
+
+pub fn seal_session(session: &Session, record_id: &[u8], storage_key: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	seal(RecordType::Session, record_id, &session.to_bytes(), storage_key, cipher)
+}
+
+pub fn open_session(record_id: &[u8], sealed: &[u8], storage_key: &[u8]) -> Result<Session, DawnCryptoError> {
+	Session::from_bytes(&open(RecordType::Session, record_id, sealed, storage_key)?)
+}
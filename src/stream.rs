@@ -0,0 +1,253 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// Streaming encryption of large data (STREAM construction)
+// The data is split into chunks which get encrypted and authenticated separately. The nonce of every chunk contains
+// its position and a flag marking the last chunk, so reordered, truncated or extended streams fail to decrypt.
+// Every stream uses a fresh key derived from the given key and a random salt, so the nonces never repeat for a key.
+
+use std::io::{self, Read, Write};
+use rand::Rng;
+use crate::hash;
+use crate::secret::SecretBytes;
+use crate::symm::{self, SymmetricCipher};
+
+const STREAM_VERSION: u8 = 1;
+pub const DEFAULT_CHUNK_SIZE: usize = 65536;
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+// version || cipher id || chunk size || salt
+const HEADER_LEN: usize = 1 + 1 + 4 + 32;
+
+fn invalid_data(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// derive the key for a single stream from the given key (e.g. from sym_key_gen) and the salt
+pub fn derive_chunk_key(key: &[u8], salt: &[u8], label: &[u8]) -> io::Result<SecretBytes> {
+	if key.len() != 32 {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "key length invalid"))
+	}
+	match hash::hkdf(key, salt, label, 32) {
+		Ok(res) => Ok(res),
+		Err(_) => Err(io::Error::other("key derivation failed"))
+	}
+}
+
+// the nonce of a chunk consists of zeros, the chunk counter and a flag marking the last chunk
+pub fn chunk_nonce(cipher: SymmetricCipher, counter: u64, last: bool) -> io::Result<Vec<u8>> {
+	let counter = match u32::try_from(counter) {
+		Ok(res) => res,
+		Err(_) => return Err(io::Error::other("too many chunks"))
+	};
+	let mut nonce = vec![0u8; cipher.nonce_len()];
+	let len = nonce.len();
+	nonce[len-5..len-1].copy_from_slice(&counter.to_be_bytes());
+	nonce[len-1] = last as u8;
+	Ok(nonce)
+}
+
+// encrypts everything written to it and writes the encrypted stream to the inner writer
+// finish() needs to be called after writing all data, otherwise the stream will be rejected as truncated
+pub struct StreamEncryptor<W: Write> {
+	writer: W,
+	key: SecretBytes,
+	cipher: SymmetricCipher,
+	header: Vec<u8>,
+	chunk_size: usize,
+	buffer: Vec<u8>,
+	counter: u64,
+}
+
+impl<W: Write> StreamEncryptor<W> {
+	pub fn new(writer: W, key: &[u8], cipher: SymmetricCipher) -> io::Result<StreamEncryptor<W>> {
+		StreamEncryptor::with_chunk_size(writer, key, cipher, DEFAULT_CHUNK_SIZE)
+	}
+	
+	pub fn with_chunk_size(mut writer: W, key: &[u8], cipher: SymmetricCipher, chunk_size: usize) -> io::Result<StreamEncryptor<W>> {
+		if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk size invalid"))
+		}
+		let salt = rand::thread_rng().gen::<[u8; 32]>();
+		let key = derive_chunk_key(key, &salt, b"dawn stream chunk key")?;
+		
+		// the header is authenticated as associated data of every chunk
+		let mut header = vec![STREAM_VERSION, cipher.id()];
+		header.extend_from_slice(&(chunk_size as u32).to_be_bytes());
+		header.extend_from_slice(&salt);
+		writer.write_all(&header)?;
+		
+		Ok(StreamEncryptor {
+			writer,
+			key,
+			cipher,
+			header,
+			chunk_size,
+			buffer: Vec::with_capacity(chunk_size),
+			counter: 0,
+		})
+	}
+	
+	fn write_chunk(&mut self, last: bool) -> io::Result<()> {
+		let nonce = chunk_nonce(self.cipher, self.counter, last)?;
+		let chunk = match symm::seal(&self.buffer, &self.key, &nonce, &self.header, self.cipher) {
+			Ok(res) => res,
+			Err(_) => return Err(io::Error::other("chunk encryption failed"))
+		};
+		self.writer.write_all(&chunk)?;
+		self.buffer.clear();
+		self.counter += 1;
+		Ok(())
+	}
+	
+	// encrypt the remaining data as the last chunk and return the inner writer
+	pub fn finish(mut self) -> io::Result<W> {
+		self.write_chunk(true)?;
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+}
+
+impl<W: Write> Write for StreamEncryptor<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if buf.is_empty() { return Ok(0) }
+		
+		// a full chunk only gets encrypted once more data follows, because it might be the last one
+		if self.buffer.len() == self.chunk_size {
+			self.write_chunk(false)?;
+		}
+		let len = buf.len().min(self.chunk_size - self.buffer.len());
+		self.buffer.extend_from_slice(&buf[..len]);
+		Ok(len)
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+}
+
+// decrypts an encrypted stream read from the inner reader
+// every chunk gets authenticated before its plaintext is returned. A modified, truncated or extended stream results in an error of kind InvalidData.
+pub struct StreamDecryptor<R: Read> {
+	reader: R,
+	key: SecretBytes,
+	cipher: SymmetricCipher,
+	header: Vec<u8>,
+	chunk_size: usize,
+	lookahead: Option<u8>,
+	plaintext: Vec<u8>,
+	position: usize,
+	counter: u64,
+	finished: bool,
+	failed: bool,
+}
+
+impl<R: Read> StreamDecryptor<R> {
+	pub fn new(mut reader: R, key: &[u8]) -> io::Result<StreamDecryptor<R>> {
+		let mut header = vec![0u8; HEADER_LEN];
+		if read_full(&mut reader, &mut header)? != HEADER_LEN {
+			return Err(invalid_data("stream header incomplete"))
+		}
+		if header[0] != STREAM_VERSION {
+			return Err(invalid_data("unsupported stream version"))
+		}
+		let cipher = match SymmetricCipher::from_id(header[1]) {
+			Some(res) => res,
+			None => return Err(invalid_data("unknown cipher"))
+		};
+		let chunk_size = u32::from_be_bytes(header[2..6].try_into().unwrap()) as usize;
+		if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+			return Err(invalid_data("chunk size invalid"))
+		}
+		let key = derive_chunk_key(key, &header[6..], b"dawn stream chunk key")?;
+		
+		Ok(StreamDecryptor {
+			reader,
+			key,
+			cipher,
+			header,
+			chunk_size,
+			lookahead: None,
+			plaintext: vec![],
+			position: 0,
+			counter: 0,
+			finished: false,
+			failed: false,
+		})
+	}
+	
+	fn read_chunk(&mut self) -> io::Result<()> {
+		let chunk_len = self.chunk_size + self.cipher.tag_len();
+		
+		// read one byte more than a chunk to find out whether this is the last chunk
+		let mut chunk = vec![0u8; chunk_len + 1];
+		let mut filled = 0;
+		if let Some(byte) = self.lookahead.take() {
+			chunk[0] = byte;
+			filled = 1;
+		}
+		filled += read_full(&mut self.reader, &mut chunk[filled..])?;
+		let last = filled <= chunk_len;
+		if !last {
+			self.lookahead = Some(chunk[chunk_len]);
+		}
+		chunk.truncate(filled.min(chunk_len));
+		
+		let nonce = chunk_nonce(self.cipher, self.counter, last)?;
+		self.plaintext = match symm::open(&chunk, &self.key, &nonce, &self.header, self.cipher) {
+			Ok(res) => res,
+			Err(_) => return Err(invalid_data("chunk authentication failed"))
+		};
+		self.position = 0;
+		self.counter += 1;
+		self.finished = last;
+		Ok(())
+	}
+}
+
+impl<R: Read> Read for StreamDecryptor<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.failed { return Err(invalid_data("stream authentication failed")) }
+		if buf.is_empty() { return Ok(0) }
+		while self.position == self.plaintext.len() {
+			if self.finished { return Ok(0) }
+			if let Err(err) = self.read_chunk() {
+				self.failed = true;
+				return Err(err)
+			}
+		}
+		let len = buf.len().min(self.plaintext.len() - self.position);
+		buf[..len].copy_from_slice(&self.plaintext[self.position..self.position + len]);
+		self.position += len;
+		Ok(len)
+	}
+}
+
+// fill the buffer as far as possible, returns less than its length only if the reader reached its end
+pub fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+	let mut filled = 0;
+	while filled < buf.len() {
+		match reader.read(&mut buf[filled..]) {
+			Ok(0) => break,
+			Ok(n) => filled += n,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err)
+		}
+	}
+	Ok(filled)
+}
@@ -16,32 +16,134 @@
 	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
 */
 
-use openssl::symm::{encrypt as openssl_encrypt, decrypt as openssl_decrypt, Cipher};
+use openssl::symm::{encrypt_aead as openssl_encrypt_aead, decrypt_aead as openssl_decrypt_aead, decrypt as openssl_decrypt, Cipher};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce, KeyInit, aead::{Aead, Payload}};
 use rand::Rng;
 
-// encrypt message using aes-256-cbc
-pub fn encrypt(data: &[u8], key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-	if key.len() != 32 {
-		return Err("key length invalid".into())
+// symmetric ciphers available for authenticated encryption
+// AES-256-GCM is fast on hardware with AES acceleration, XChaCha20-Poly1305 is the better choice without it
+// and its 192-bit random nonces make it safe to encrypt a practically unlimited number of messages under the same key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymmetricCipher {
+	Aes256Gcm,
+	XChaCha20Poly1305,
+}
+
+impl SymmetricCipher {
+	pub fn nonce_len(&self) -> usize {
+		match self {
+			SymmetricCipher::Aes256Gcm => 12,
+			SymmetricCipher::XChaCha20Poly1305 => 24,
+		}
+	}
+	
+	pub fn tag_len(&self) -> usize {
+		16
+	}
+	
+	// identifier used to record the cipher in encrypted formats
+	pub fn id(&self) -> u8 {
+		match self {
+			SymmetricCipher::Aes256Gcm => 1,
+			SymmetricCipher::XChaCha20Poly1305 => 2,
+		}
 	}
-	let iv = rand::thread_rng().gen::<[u8; 16]>();
-	let aes_cipher = Cipher::aes_256_cbc();
-	let mut enc_msg = openssl_encrypt(aes_cipher, key, Some(&iv), data)?;
-	let mut ciphertext = iv.to_vec();
+	
+	pub fn from_id(id: u8) -> Option<SymmetricCipher> {
+		match id {
+			1 => Some(SymmetricCipher::Aes256Gcm),
+			2 => Some(SymmetricCipher::XChaCha20Poly1305),
+			_ => None
+		}
+	}
+}
+
+// encrypt message using the selected cipher, authenticating the ciphertext and the associated data
+// the output is nonce || ciphertext || tag
+pub fn encrypt_aead(data: &[u8], key: &[u8], aad: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	let mut nonce = vec![0u8; cipher.nonce_len()];
+	rand::thread_rng().fill(&mut nonce[..]);
+	let mut enc_msg = seal(data, key, &nonce, aad, cipher)?;
+	let mut ciphertext = nonce;
 	ciphertext.append(&mut enc_msg);
 	Ok(ciphertext)
 }
 
-// decrypt message using aes-256-cbc
-pub fn decrypt(ciphertext: &[u8], key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+// decrypt message using the selected cipher
+// the tag gets verified before any plaintext is returned, so tampered ciphertexts or associated data result in an error
+pub fn decrypt_aead(ciphertext: &[u8], key: &[u8], aad: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	// check the length of the ciphertext
+	if ciphertext.len() < cipher.nonce_len() + cipher.tag_len() { return Err("ciphertext too short".into()) }
+	let (nonce, enc_data) = ciphertext.split_at(cipher.nonce_len());
+	open(enc_data, key, nonce, aad, cipher)
+}
+
+// encrypt data with an explicitly given nonce, the output is ciphertext || tag
+// the caller is responsible for never reusing a nonce with the same key
+pub fn seal(data: &[u8], key: &[u8], nonce: &[u8], aad: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
 	if key.len() != 32 {
 		return Err("key length invalid".into())
 	}
-	let mut ciphertext = ciphertext.to_vec();
-	// check the length of the ciphertext
-	if ciphertext.len() <= 16 { return Err("ciphertext too short".into()) }
-	let enc_data = ciphertext.split_off(16);
-	let aes_cipher = Cipher::aes_256_cbc();
-	let dec_msg = openssl_decrypt(aes_cipher, key, Some(&ciphertext), &enc_data)?;
+	if nonce.len() != cipher.nonce_len() {
+		return Err("nonce length invalid".into())
+	}
+	match cipher {
+		SymmetricCipher::Aes256Gcm => {
+			let mut tag = [0u8; 16];
+			let mut ciphertext = openssl_encrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), aad, data, &mut tag)?;
+			ciphertext.extend_from_slice(&tag);
+			Ok(ciphertext)
+		}
+		SymmetricCipher::XChaCha20Poly1305 => {
+			let chacha_cipher = XChaCha20Poly1305::new_from_slice(key)?;
+			let nonce = XNonce::try_from(nonce)?;
+			match chacha_cipher.encrypt(&nonce, Payload { msg: data, aad }) {
+				Ok(ciphertext) => Ok(ciphertext),
+				Err(_) => Err("encryption failed".into())
+			}
+		}
+	}
+}
+
+// decrypt data (ciphertext || tag) with an explicitly given nonce
+pub fn open(ciphertext: &[u8], key: &[u8], nonce: &[u8], aad: &[u8], cipher: SymmetricCipher) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	if key.len() != 32 {
+		return Err("key length invalid".into())
+	}
+	if nonce.len() != cipher.nonce_len() {
+		return Err("nonce length invalid".into())
+	}
+	if ciphertext.len() < cipher.tag_len() { return Err("ciphertext too short".into()) }
+	match cipher {
+		SymmetricCipher::Aes256Gcm => {
+			let (enc_data, tag) = ciphertext.split_at(ciphertext.len() - 16);
+			let dec_msg = openssl_decrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), aad, enc_data, tag)?;
+			Ok(dec_msg)
+		}
+		SymmetricCipher::XChaCha20Poly1305 => {
+			let chacha_cipher = XChaCha20Poly1305::new_from_slice(key)?;
+			let nonce = XNonce::try_from(nonce)?;
+			match chacha_cipher.decrypt(&nonce, Payload { msg: ciphertext, aad }) {
+				Ok(dec_msg) => Ok(dec_msg),
+				Err(_) => Err("authentication failed".into())
+			}
+		}
+	}
+}
+
+// decrypt data encrypted using aes-256-cbc by earlier versions, the output of those was iv || ciphertext
+// this is not authenticated, so only use it to migrate existing data
+pub fn decrypt_cbc_legacy(ciphertext: &[u8], key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	if key.len() != 32 {
+		return Err("key length invalid".into())
+	}
+	if !is_cbc_legacy(ciphertext) { return Err("not a legacy ciphertext".into()) }
+	let (iv, enc_data) = ciphertext.split_at(16);
+	let dec_msg = openssl_decrypt(Cipher::aes_256_cbc(), key, Some(iv), enc_data)?;
 	Ok(dec_msg)
 }
+
+// legacy ciphertexts consist of a 16-byte iv and at least one 16-byte block
+pub fn is_cbc_legacy(ciphertext: &[u8]) -> bool {
+	ciphertext.len() >= 32 && ciphertext.len().is_multiple_of(16)
+}
@@ -29,14 +29,14 @@ use chrono::prelude::*;
 fn test_message_encryption() {
 	// init test environment
 	let (pk, sk) = kyber_keygen();
-	let (sign_pk, sign_sk) = sign_keygen();
-	let (other_sign_pk, other_sign_sk) = sign_keygen();
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
+	let (other_sign_pk, other_sign_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
 	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
 	let salt = rand::thread_rng().gen::<[u8;32]>();
 	
 	// test encrypted and signed message
-	let (enc_msg, new_key) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "testing message encryption").unwrap();
-	assert_ne!(pfs_key.to_vec(), new_key);
+	let (enc_msg, new_key) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "testing message encryption", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_ne!(&pfs_key[..], &new_key[..]);
 	assert_eq!(new_key.len(), 32);
 	let (dec_msg, other_new_key, warning) = decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
 	assert_eq!(new_key, other_new_key);
@@ -50,8 +50,8 @@ fn test_message_encryption() {
 	assert_eq!(warning, warning::NONE);
 	
 	// test encrypted and unsigned message
-	let (enc_msg, new_key) = encrypt_msg(&pk, None, &pfs_key, &salt, "testing message encryption").unwrap();
-	assert_ne!(pfs_key.to_vec(), new_key);
+	let (enc_msg, new_key) = encrypt_msg(&pk, None, &pfs_key, &salt, "testing message encryption", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_ne!(&pfs_key[..], &new_key[..]);
 	assert_eq!(new_key.len(), 32);
 	let (dec_msg, other_new_key, warning) = decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
 	assert_eq!(new_key, other_new_key);
@@ -59,10 +59,592 @@ fn test_message_encryption() {
 	assert_eq!(warning, warning::NO_SIGNATURE);
 	
 	// test encrypted and wrongly signed message
-	let (enc_msg, new_key) = encrypt_msg(&pk, Some(&other_sign_sk), &pfs_key, &salt, "testing message encryption").unwrap();
-	assert_ne!(pfs_key.to_vec(), new_key);
+	let (enc_msg, new_key) = encrypt_msg(&pk, Some(&other_sign_sk), &pfs_key, &salt, "testing message encryption", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_ne!(&pfs_key[..], &new_key[..]);
 	assert_eq!(new_key.len(), 32);
-	assert_eq!(decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg), Err("@dawn-crypto: signature verification failed".to_string()));
+	assert!(matches!(debug::decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg), Err(DawnCryptoError::SignatureInvalid(_))));
+	
+	// test message encrypted with XChaCha20-Poly1305
+	let (enc_msg, new_key) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "testing message encryption", SymmetricCipher::XChaCha20Poly1305).unwrap();
+	let (dec_msg, other_new_key, warning) = decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
+	assert_eq!(new_key, other_new_key);
+	assert_eq!(dec_msg, "testing message encryption".to_string());
+	assert_eq!(warning, warning::NONE);
+}
+
+#[test]
+fn test_message_envelope() {
+	let (pk, sk) = kyber_keygen();
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	let (enc_msg, _) = encrypt_msg(&pk, None, &pfs_key, &salt, "testing envelope", SymmetricCipher::XChaCha20Poly1305).unwrap();
+	let envelope = MessageEnvelope::parse(&enc_msg).unwrap();
+	assert_eq!(envelope.version, envelope::VERSION);
+	assert_eq!(envelope.cipher, SymmetricCipher::XChaCha20Poly1305);
+	assert_eq!(envelope.kem_ciphertext.len(), 1568);
+	assert_eq!(envelope.encode(), enc_msg);
+	
+	// unknown versions and algorithms get rejected
+	let mut modified = enc_msg.clone();
+	modified[1] = 42;
+	assert!(matches!(MessageEnvelope::parse(&modified), Err(DawnCryptoError::UnsupportedVersion(42))));
+	assert!(matches!(debug::decrypt_msg(&sk, None, &pfs_key, &salt, &modified), Err(DawnCryptoError::UnsupportedVersion(42))));
+	let mut modified = enc_msg.clone();
+	modified[2] = 0x1f;
+	assert!(matches!(MessageEnvelope::parse(&modified), Err(DawnCryptoError::UnsupportedAlgorithm(0x1f))));
+	
+	// changing the cipher in the header is detected
+	let mut modified = enc_msg.clone();
+	modified[2] = 0x11;
+	assert!(decrypt_msg(&sk, None, &pfs_key, &salt, &modified).is_err());
+	
+	// truncated or extended envelopes get rejected
+	assert!(MessageEnvelope::parse(&enc_msg[..enc_msg.len() - 1]).is_err());
+	assert!(MessageEnvelope::parse(&[&enc_msg[..], &[0u8]].concat()).is_err());
+}
+
+#[test]
+fn test_legacy_message_format() {
+	// a message and data encrypted by the version before the envelope format, using aes-256-cbc
+	let fixture: Vec<Vec<u8>> = include_str!("testdata/baseline.hex").lines().map(|line| hex::decode(line).unwrap()).collect();
+	let (sk, pfs_key, salt, enc_msg, new_pfs_key) = (&fixture[0], &fixture[1], &fixture[2], &fixture[3], &fixture[4]);
+	let (dec_msg, other_new_key, warning) = decrypt_msg_compat(sk, None, pfs_key, salt, enc_msg).unwrap();
+	assert_eq!(dec_msg, "sent before the envelope format");
+	assert_eq!(&other_new_key[..], &new_pfs_key[..]);
+	assert_eq!(warning, warning::LEGACY_FORMAT | warning::NO_SIGNATURE);
+	assert!(decrypt_msg_compat(sk, None, new_pfs_key, salt, enc_msg).is_err());
+	assert!(matches!(decrypt_msg(sk, None, pfs_key, salt, enc_msg), Err(DawnCryptoError::DecryptionFailed)));
+	assert!(matches!(decrypt_data(&fixture[6], &fixture[5], SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::DecryptionFailed)));
+	assert_eq!(decrypt_data_compat(&fixture[6], &fixture[5], SymmetricCipher::Aes256Gcm).unwrap(), (b"stored before authenticated encryption".to_vec(), warning::LEGACY_FORMAT));
+}
+
+#[test]
+fn test_binary_message_encryption() {
+	let (pk, sk) = kyber_keygen();
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	
+	// invalid UTF-8 and separators used by the old text format need to survive unchanged
+	let msg = vec![0xff, 0xfe, b'.', 0, b'.', 0x80];
+	let (enc_msg, new_key) = encrypt_msg_bytes(&pk, Some(&sign_sk), &pfs_key, &salt, &msg, SymmetricCipher::Aes256Gcm).unwrap();
+	let (dec_msg, other_new_key, warning) = decrypt_msg_bytes(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
+	assert_eq!(dec_msg, msg);
+	assert_eq!(new_key, other_new_key);
+	assert_eq!(warning, warning::NONE);
+	
+	// the text API doesn't replace invalid UTF-8 silently
+	assert!(matches!(debug::decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg), Err(DawnCryptoError::InvalidMessage(_))));
+	
+	// empty messages
+	let (enc_msg, _) = encrypt_msg_bytes(&pk, None, &pfs_key, &salt, &[], SymmetricCipher::Aes256Gcm).unwrap();
+	let (dec_msg, _, warning) = decrypt_msg_bytes(&sk, None, &pfs_key, &salt, &enc_msg).unwrap();
+	assert!(dec_msg.is_empty());
+	assert_eq!(warning, warning::NO_SIGNATURE);
+}
+
+#[test]
+fn test_signature_binding() {
+	let (bob_pk, bob_sk) = kyber_keygen();
+	let (carol_pk, carol_sk) = kyber_keygen();
+	let (alice_sign_pk, alice_sign_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	assert_eq!(kyber::public_key_from_secret(&bob_sk).unwrap(), bob_pk);
+	
+	// Alice sends a signed message to Bob
+	let (enc_msg, _) = encrypt_msg(&bob_pk, Some(&alice_sign_sk), &pfs_key, &salt, "for Bob only", SymmetricCipher::Aes256Gcm).unwrap();
+	assert!(decrypt_msg(&bob_sk, Some(&alice_sign_pk), &pfs_key, &salt, &enc_msg).is_ok());
+	
+	// Bob decrypts it and re-encrypts the signed payload to Carol
+	let envelope = MessageEnvelope::parse(&enc_msg).unwrap();
+	let kyber_shared_secret = kyber::decrypt_shared_secret(&envelope.kem_ciphertext, &bob_sk).unwrap();
+	let new_pfs_key = kdf::get_pfs_key(&pfs_key, &salt).unwrap();
+	let secret = kdf::message_key(&kyber_shared_secret, &new_pfs_key).unwrap();
+	let signed_payload = symm::decrypt_aead(&envelope.payload, &secret, &envelope.associated_data(), envelope.cipher).unwrap();
+	
+	let reencrypt = |version: u8, payload: &[u8]| {
+		let (kyber_shared_secret, kyber_ciphertext) = kyber::get_shared_secret(&carol_pk).unwrap();
+		let secret = kdf::message_key(&kyber_shared_secret, &new_pfs_key).unwrap();
+		let mut envelope = MessageEnvelope { version, kem: 1, cipher: SymmetricCipher::Aes256Gcm, kem_ciphertext: kyber_ciphertext, payload: vec![] };
+		envelope.payload = symm::encrypt_aead(payload, &secret, &envelope.associated_data(), envelope.cipher).unwrap();
+		envelope.encode()
+	};
+	
+	// the forwarded message decrypts, but its signature doesn't verify for Carol
+	let forwarded = reencrypt(envelope::VERSION, &signed_payload);
+	assert!(decrypt_msg(&carol_sk, None, &pfs_key, &salt, &forwarded).is_ok());
+	assert!(matches!(debug::decrypt_msg(&carol_sk, Some(&alice_sign_pk), &pfs_key, &salt, &forwarded), Err(DawnCryptoError::SignatureInvalid(_))));
+	
+	// there is no other envelope version to downgrade to
+	let forwarded = reencrypt(envelope::VERSION + 1, &signed_payload);
+	assert!(matches!(MessageEnvelope::parse(&forwarded), Err(DawnCryptoError::UnsupportedVersion(_))));
+	assert!(decrypt_msg(&carol_sk, Some(&alice_sign_pk), &pfs_key, &salt, &forwarded).is_err());
+	
+	// a signature made for another PFS epoch doesn't verify
+	let other_pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let signature = sign::sign(&alice_sign_sk, &envelope::signed_data(envelope.kem, &bob_pk, &envelope.kem_ciphertext, &other_pfs_key, b"for Bob only").unwrap()).unwrap();
+	let mut modified = envelope.clone();
+	modified.payload = symm::encrypt_aead(&padding::pad(&envelope::encode_payload(&signature, b"for Bob only"), Padding::None), &secret, &envelope.associated_data(), envelope.cipher).unwrap();
+	assert!(matches!(debug::decrypt_msg(&bob_sk, Some(&alice_sign_pk), &pfs_key, &salt, &modified.encode()), Err(DawnCryptoError::SignatureInvalid(_))));
+}
+
+#[test]
+fn test_signature_algorithms() {
+	let (pk, sk) = kyber_keygen();
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	for algorithm in [SignatureAlgorithm::MlDsa65, SignatureAlgorithm::MlDsa87] {
+		let (sign_pk, sign_sk) = sign_keygen(algorithm);
+		assert_eq!(get_signature_algorithm(&sign_pk).unwrap(), algorithm);
+		assert_eq!(get_signature_algorithm(&sign_sk).unwrap(), algorithm);
+		
+		let (enc_msg, new_key) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "testing ML-DSA", SymmetricCipher::Aes256Gcm).unwrap();
+		let (dec_msg, other_new_key, warning) = decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
+		assert_eq!(dec_msg, "testing ML-DSA");
+		assert_eq!(new_key, other_new_key);
+		assert_eq!(warning, warning::NONE);
+		
+		// signatures are tagged with the algorithm and a lot smaller than SPHINCS+ signatures
+		let signature = sign::sign(&sign_sk, b"message").unwrap();
+		assert_eq!(signature[0], algorithm.id());
+		assert!(signature.len() < 5000);
+		assert!(sign::verify(&signature, &sign_pk, b"message").is_ok());
+		assert!(sign::verify(&signature, &sign_pk, b"other message").is_err());
+	}
+	
+	// signatures don't verify with a key of another algorithm
+	let (_, mldsa_sk) = sign_keygen(SignatureAlgorithm::MlDsa65);
+	let (other_pk, _) = sign_keygen(SignatureAlgorithm::MlDsa87);
+	let (enc_msg, _) = encrypt_msg(&pk, Some(&mldsa_sk), &pfs_key, &salt, "", SymmetricCipher::Aes256Gcm).unwrap();
+	assert!(matches!(debug::decrypt_msg(&sk, Some(&other_pk), &pfs_key, &salt, &enc_msg), Err(DawnCryptoError::SignatureInvalid(_))));
+	
+	// SPHINCS+ keys without tag keep working and produce signatures without tag
+	let (sphincs_pk, sphincs_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
+	let signature = sign::sign(&sphincs_sk[1..], b"message").unwrap();
+	assert_eq!(signature.len(), sign::sign(&sphincs_sk, b"message").unwrap().len() - 1);
+	assert!(sign::verify(&signature, &sphincs_pk[1..], b"message").is_ok());
+	assert!(sign::verify(&signature, &sphincs_pk, b"message").is_ok());
+	assert_eq!(get_signature_algorithm(&sphincs_pk[1..]).unwrap(), SignatureAlgorithm::SphincsShake192f);
+}
+
+#[test]
+fn test_composite_signatures() {
+	let (pk, sk) = kyber_keygen();
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	for algorithm in [SignatureAlgorithm::Ed25519MlDsa65, SignatureAlgorithm::Ed25519SphincsShake192f] {
+		let (sign_pk, sign_sk) = sign_keygen(algorithm);
+		let (enc_msg, _) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "testing composite signatures", SymmetricCipher::Aes256Gcm).unwrap();
+		let (dec_msg, _, warning) = decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
+		assert_eq!(dec_msg, "testing composite signatures");
+		assert_eq!(warning, warning::NONE);
+	}
+	
+	// the signature is invalid as soon as one of the parts is
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::Ed25519MlDsa65);
+	let (_, other_sign_sk) = sign_keygen(SignatureAlgorithm::Ed25519MlDsa65);
+	let signature = sign::sign(&sign_sk, b"message").unwrap();
+	let other_signature = sign::sign(&other_sign_sk, b"message").unwrap();
+	assert!(sign::verify(&signature, &sign_pk, b"message").is_ok());
+	let wrong_ed25519 = [&other_signature[..65], &signature[65..]].concat();
+	let wrong_mldsa = [&signature[..65], &other_signature[65..]].concat();
+	assert!(sign::verify(&wrong_ed25519, &sign_pk, b"message").is_err());
+	assert!(sign::verify(&wrong_mldsa, &sign_pk, b"message").is_err());
+	
+	// the ML-DSA part doesn't work as a signature on its own
+	let mldsa_pk = [&[SignatureAlgorithm::MlDsa65.id()], &sign_pk[33..]].concat();
+	let mldsa_signature = [&[SignatureAlgorithm::MlDsa65.id()], &signature[65..]].concat();
+	assert!(sign::verify(&mldsa_signature, &mldsa_pk, b"message").is_err());
+	
+	// identity material
+	let identity = [pk.clone(), sign_pk.clone()].concat();
+	let signature = sign_identity(&sign_sk, &identity).unwrap();
+	assert!(verify_identity(&signature, &sign_pk, &identity).is_ok());
+	assert!(matches!(verify_identity(&signature, &sign_pk, &pk), Err(DawnCryptoError::SignatureInvalid(_))));
+	assert!(sign::verify(&signature, &sign_pk, &identity).is_err());
+}
+
+#[test]
+fn test_mlkem_message_encryption() {
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	for (algorithm, pk_len, ct_len) in [(KemAlgorithm::MlKem768, 1184, 1088), (KemAlgorithm::MlKem1024, 1568, 1568)] {
+		let (pk, sk) = kem_keygen(algorithm);
+		assert_eq!(pk.len(), pk_len + 1);
+		assert_eq!(get_kem_algorithm(&pk).unwrap(), algorithm);
+		assert_eq!(get_kem_algorithm(&sk).unwrap(), algorithm);
+		assert_eq!(kem::public_key_from_secret(&sk).unwrap(), pk);
+		
+		let (enc_msg, new_key) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "testing ML-KEM", SymmetricCipher::Aes256Gcm).unwrap();
+		let envelope = MessageEnvelope::parse(&enc_msg).unwrap();
+		assert_eq!(envelope.kem, algorithm.id());
+		assert_eq!(envelope.kem_ciphertext.len(), ct_len);
+		let (dec_msg, other_new_key, warning) = decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
+		assert_eq!(dec_msg, "testing ML-KEM");
+		assert_eq!(new_key, other_new_key);
+		assert_eq!(warning, warning::NONE);
+		
+		let (shared_secret, ciphertext) = get_kyber_secret(&pk).unwrap();
+		assert_eq!(decrypt_kyber_secret(&ciphertext, &sk).unwrap(), shared_secret);
+	}
+	
+	// a message for another algorithm gets rejected instead of being decapsulated with the wrong key
+	let (pk, _) = kem_keygen(KemAlgorithm::MlKem768);
+	let (_, sk) = kem_keygen(KemAlgorithm::MlKem1024);
+	let (enc_msg, _) = encrypt_msg(&pk, None, &pfs_key, &salt, "wrong algorithm", SymmetricCipher::Aes256Gcm).unwrap();
+	assert!(matches!(debug::decrypt_msg(&sk, None, &pfs_key, &salt, &enc_msg), Err(DawnCryptoError::UnsupportedAlgorithm(_))));
+	
+	// unknown tags and wrong lengths of tagged keys
+	let mut modified = pk.clone();
+	modified[0] = 0x0f;
+	assert!(matches!(encrypt_msg(&modified, None, &pfs_key, &salt, "", SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::UnsupportedAlgorithm(0x0f))));
+	assert!(matches!(encrypt_msg(&pk[..pk.len() - 1], None, &pfs_key, &salt, "", SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::InvalidKeyLength { .. })));
+}
+
+#[test]
+fn test_legacy_kyber_keys() {
+	// untagged keys from kyber_keygen and tagged kyber keys are interchangeable
+	let (pk, sk) = kyber_keygen();
+	assert_eq!(get_kem_algorithm(&pk).unwrap(), KemAlgorithm::Kyber1024);
+	let tagged_sk = [&[KemAlgorithm::Kyber1024.id()], &sk[..]].concat();
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	let (enc_msg, _) = encrypt_msg(&pk, None, &pfs_key, &salt, "legacy key", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(decrypt_msg(&sk, None, &pfs_key, &salt, &enc_msg).unwrap().0, "legacy key");
+	assert_eq!(decrypt_msg(&tagged_sk, None, &pfs_key, &salt, &enc_msg).unwrap().0, "legacy key");
+	
+	let (pk, sk) = kem_keygen(KemAlgorithm::Kyber1024);
+	let (enc_msg, _) = encrypt_msg(&pk, None, &pfs_key, &salt, "tagged key", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(decrypt_msg(&sk[1..], None, &pfs_key, &salt, &enc_msg).unwrap().0, "tagged key");
+	
+	// signatures cover the key independently of its encoding
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::MlDsa65);
+	let (enc_msg, _) = encrypt_msg(&pk[1..], Some(&sign_sk), &pfs_key, &salt, "signed for the untagged key", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap().0, "signed for the untagged key");
+	let (enc_msg, _) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "signed for the tagged key", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(decrypt_msg(&sk[1..], Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap().0, "signed for the tagged key");
+}
+
+#[test]
+fn test_hybrid_message_encryption() {
+	let (pk, sk) = hybrid_keygen();
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	assert_eq!(hybrid::public_key_from_secret(&sk).unwrap(), pk);
+	
+	let (enc_msg, new_key) = encrypt_msg_hybrid(&pk, Some(&sign_sk), &pfs_key, &salt, "testing hybrid encryption", SymmetricCipher::Aes256Gcm).unwrap();
+	let envelope = MessageEnvelope::parse(&enc_msg).unwrap();
+	assert_eq!(envelope.kem, 2);
+	assert_eq!(envelope.kem_ciphertext.len(), 32 + 1568);
+	let (dec_msg, other_new_key, warning) = decrypt_msg_hybrid(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
+	assert_eq!(dec_msg, "testing hybrid encryption");
+	assert_eq!(new_key, other_new_key);
+	assert_eq!(warning, warning::NONE);
+	
+	// hybrid messages don't get mistaken for kyber-only messages and vice versa
+	let (kyber_pk, kyber_sk) = kyber_keygen();
+	assert!(matches!(debug::decrypt_msg(&kyber_sk, None, &pfs_key, &salt, &enc_msg), Err(DawnCryptoError::UnsupportedAlgorithm(_))));
+	let (enc_msg, _) = encrypt_msg(&kyber_pk, None, &pfs_key, &salt, "kyber only", SymmetricCipher::Aes256Gcm).unwrap();
+	assert!(matches!(debug::decrypt_msg_hybrid(&sk, None, &pfs_key, &salt, &enc_msg), Err(DawnCryptoError::UnsupportedAlgorithm(_))));
+}
+
+#[test]
+fn test_hybrid_kem() {
+	let (pk, sk) = hybrid::keygen();
+	let (shared_secret, ciphertext) = hybrid::get_shared_secret(&pk).unwrap();
+	assert_eq!(hybrid::decrypt_shared_secret(&ciphertext, &sk).unwrap(), shared_secret);
+	let (curve_ciphertext, kyber_ciphertext) = ciphertext.split_at(32);
+	let (curve_sk, kyber_sk) = sk.split_at(32);
+	
+	// the key is combined from the shared secrets of both halves
+	let kyber_secret = kyber::decrypt_shared_secret(kyber_ciphertext, kyber_sk).unwrap();
+	let curve_secret = x25519::get_shared_secret(curve_sk, curve_ciphertext).unwrap();
+	assert_eq!(hybrid::combine(&kyber_secret, &curve_secret, curve_ciphertext, &pk[..32]).unwrap(), shared_secret);
+	
+	// the key is bound to the x25519 ciphertext and public key, not only to the two secrets
+	let (other_curve_pk, _) = x25519::keygen();
+	assert_ne!(hybrid::combine(&kyber_secret, &curve_secret, &other_curve_pk, &pk[..32]).unwrap(), shared_secret);
+	assert_ne!(hybrid::combine(&kyber_secret, &curve_secret, curve_ciphertext, &other_curve_pk).unwrap(), shared_secret);
+	assert_ne!(hybrid::combine(&kyber_secret, &curve_secret, &pk[..32], curve_ciphertext).unwrap(), shared_secret);
+	
+	// the same holds for a secret key of which only one half is correct
+	let (_, other_sk) = hybrid::keygen();
+	let wrong_curve_sk = [&other_sk[..32], kyber_sk].concat();
+	let wrong_kyber_sk = [curve_sk, &other_sk[32..]].concat();
+	assert_ne!(hybrid::decrypt_shared_secret(&ciphertext, &wrong_curve_sk).unwrap(), shared_secret);
+	assert_ne!(hybrid::decrypt_shared_secret(&ciphertext, &wrong_kyber_sk).unwrap(), shared_secret);
+	
+	// swapping one half of the ciphertext changes the key
+	let (_, other_ciphertext) = hybrid::get_shared_secret(&pk).unwrap();
+	let mixed = [&other_ciphertext[..32], kyber_ciphertext].concat();
+	assert_ne!(hybrid::decrypt_shared_secret(&mixed, &sk).unwrap(), shared_secret);
+}
+
+#[test]
+fn test_ratchet() {
+	let shared_secret = rand::thread_rng().gen::<[u8; 32]>();
+	let (bob_ratchet_pk, bob_ratchet_sk) = hybrid_keygen();
+	let mut alice = Ratchet::initiate(&shared_secret, &bob_ratchet_pk, SymmetricCipher::Aes256Gcm).unwrap();
+	let mut bob = Ratchet::respond(&shared_secret, (bob_ratchet_pk, bob_ratchet_sk), SymmetricCipher::XChaCha20Poly1305).unwrap();
+	
+	// Bob doesn't know a ratchet key of Alice yet
+	assert!(matches!(bob.encrypt(b"too early"), Err(DawnCryptoError::InvalidInput(_))));
+	
+	// several turns with several messages each
+	for turn in 0..4 {
+		let (sender, receiver) = match turn % 2 {
+			0 => (&mut alice, &mut bob),
+			_ => (&mut bob, &mut alice)
+		};
+		let mut previous = vec![];
+		for i in 0..3 {
+			let msg = format!("turn {} message {}", turn, i);
+			let enc_msg = sender.encrypt(msg.as_bytes()).unwrap();
+			assert_ne!(enc_msg, previous);
+			assert_eq!(receiver.decrypt(&enc_msg).unwrap(), msg.as_bytes());
+			previous = enc_msg;
+		}
+	}
+	
+	// a copy of the state taken mid-conversation can't decrypt anything after the next reply turn
+	let stolen = Ratchet::from_bytes(&bob.to_bytes()).unwrap();
+	let enc_msg = alice.encrypt(b"before").unwrap();
+	assert_eq!(stolen.clone().decrypt(&enc_msg).unwrap(), b"before");
+	assert_eq!(bob.decrypt(&enc_msg).unwrap(), b"before");
+	assert_eq!(alice.decrypt(&bob.encrypt(b"reply").unwrap()).unwrap(), b"reply");
+	let enc_msg = alice.encrypt(b"after").unwrap();
+	assert!(stolen.clone().decrypt(&enc_msg).is_err());
+	assert_eq!(bob.decrypt(&enc_msg).unwrap(), b"after");
+	
+	// a manipulated message doesn't change the state of the receiver
+	let enc_msg = alice.encrypt(b"authentic").unwrap();
+	let mut modified = enc_msg.clone();
+	let len = modified.len();
+	modified[len - 1] ^= 1;
+	assert!(matches!(bob.decrypt(&modified), Err(DawnCryptoError::DecryptionFailed)));
+	assert!(matches!(debug::decrypt_ratchet(&mut bob, &modified), Err(DawnCryptoError::SymmetricDecryption(_))));
+	assert_eq!(bob.decrypt(&enc_msg).unwrap(), b"authentic");
+	
+	// a session with another shared secret can't decrypt anything
+	let (carol_ratchet_pk, carol_ratchet_sk) = hybrid_keygen();
+	let mut alice = Ratchet::initiate(&shared_secret, &carol_ratchet_pk, SymmetricCipher::Aes256Gcm).unwrap();
+	let mut carol = Ratchet::respond(&rand::thread_rng().gen::<[u8; 32]>(), (carol_ratchet_pk, carol_ratchet_sk), SymmetricCipher::Aes256Gcm).unwrap();
+	assert!(carol.decrypt(&alice.encrypt(b"secret").unwrap()).is_err());
+}
+
+#[test]
+fn test_ratchet_out_of_order() {
+	let shared_secret = rand::thread_rng().gen::<[u8; 32]>();
+	let (bob_ratchet_pk, bob_ratchet_sk) = hybrid_keygen();
+	let mut alice = Ratchet::initiate(&shared_secret, &bob_ratchet_pk, SymmetricCipher::Aes256Gcm).unwrap();
+	let mut bob = Ratchet::respond(&shared_secret, (bob_ratchet_pk, bob_ratchet_sk), SymmetricCipher::Aes256Gcm).unwrap();
+	
+	// the first message of a turn gets lost, the others arrive in the wrong order
+	let messages: Vec<Vec<u8>> = (0..4).map(|i| alice.encrypt(format!("message {}", i).as_bytes()).unwrap()).collect();
+	assert_eq!(bob.decrypt(&messages[2]).unwrap(), b"message 2");
+	assert_eq!(bob.decrypt(&messages[1]).unwrap(), b"message 1");
+	assert_eq!(bob.decrypt(&messages[3]).unwrap(), b"message 3");
+	
+	// the cached key gets deleted after use
+	assert!(bob.decrypt(&messages[1]).is_err());
+	
+	// a message of a previous turn arrives after the next turn started
+	assert_eq!(alice.decrypt(&bob.encrypt(b"reply").unwrap()).unwrap(), b"reply");
+	let late = alice.encrypt(b"late").unwrap();
+	assert_eq!(bob.decrypt(&alice.encrypt(b"next").unwrap()).unwrap(), b"next");
+	assert_eq!(alice.decrypt(&bob.encrypt(b"reply 2").unwrap()).unwrap(), b"reply 2");
+	assert_eq!(bob.decrypt(&alice.encrypt(b"new turn").unwrap()).unwrap(), b"new turn");
+	assert_eq!(bob.decrypt(&late).unwrap(), b"late");
+	assert_eq!(bob.decrypt(&messages[0]).unwrap(), b"message 0");
+	
+	// skipping too many messages is refused
+	for _ in 0..ratchet::MAX_SKIP + 1 {
+		alice.encrypt(b"lost").unwrap();
+	}
+	let too_far = alice.encrypt(b"too far").unwrap();
+	assert!(matches!(bob.decrypt(&too_far), Err(DawnCryptoError::DecryptionFailed)));
+	assert!(matches!(debug::decrypt_ratchet(&mut bob, &too_far), Err(DawnCryptoError::InvalidMessage(_))));
+}
+
+#[test]
+fn test_ratchet_replay() {
+	let shared_secret = rand::thread_rng().gen::<[u8; 32]>();
+	let (bob_ratchet_pk, bob_ratchet_sk) = hybrid_keygen();
+	let mut alice = Ratchet::initiate(&shared_secret, &bob_ratchet_pk, SymmetricCipher::Aes256Gcm).unwrap();
+	let mut bob = Ratchet::respond(&shared_secret, (bob_ratchet_pk, bob_ratchet_sk), SymmetricCipher::Aes256Gcm).unwrap();
+	
+	let first = alice.encrypt(b"first").unwrap();
+	let second = alice.encrypt(b"second").unwrap();
+	let third = alice.encrypt(b"third").unwrap();
+	assert_eq!(bob.decrypt(&first).unwrap(), b"first");
+	assert_eq!(bob.decrypt(&third).unwrap(), b"third");
+	assert!(matches!(debug::decrypt_ratchet(&mut bob, &first), Err(DawnCryptoError::Replay(0))));
+	assert!(matches!(debug::decrypt_ratchet(&mut bob, &third), Err(DawnCryptoError::Replay(2))));
+	
+	// the sequence number is checked before the message is authenticated, so a replay isn't reported in detail
+	assert!(matches!(bob.decrypt(&first), Err(DawnCryptoError::DecryptionFailed)));
+	
+	// a delayed message is accepted once
+	assert_eq!(bob.decrypt(&second).unwrap(), b"second");
+	assert!(matches!(debug::decrypt_ratchet(&mut bob, &second), Err(DawnCryptoError::Replay(1))));
+	
+	// replays of a previous turn are detected as well
+	assert_eq!(alice.decrypt(&bob.encrypt(b"reply").unwrap()).unwrap(), b"reply");
+	assert_eq!(bob.decrypt(&alice.encrypt(b"next turn").unwrap()).unwrap(), b"next turn");
+	assert!(matches!(debug::decrypt_ratchet(&mut bob, &first), Err(DawnCryptoError::Replay(0))));
+	
+	// the sequence number is authenticated, so it can't be changed to get a replay through
+	let last = alice.encrypt(b"last").unwrap();
+	let mut modified = last.clone();
+	let sequence_end = modified.len() - 4 - 12 - Padding::Padme.padded_len("last".len()) - 16;
+	modified[sequence_end - 1] ^= 0x80;
+	assert!(matches!(bob.decrypt(&modified), Err(DawnCryptoError::DecryptionFailed)));
+	assert_eq!(bob.decrypt(&last).unwrap(), b"last");
+	assert!(matches!(debug::decrypt_ratchet(&mut bob, &last), Err(DawnCryptoError::Replay(_))));
+}
+
+#[test]
+fn test_session() {
+	let (alice_sign_pk, alice_sign_sk) = typed::sign_keygen(SignatureAlgorithm::Ed25519MlDsa65);
+	let (bob_sign_pk, bob_sign_sk) = typed::sign_keygen(SignatureAlgorithm::MlDsa65);
+	let shared_secret = rand::thread_rng().gen::<[u8; 32]>();
+	let (bob_ratchet_pk, bob_ratchet_sk) = hybrid_keygen();
+	let alice_ratchet = Ratchet::initiate(&shared_secret, &bob_ratchet_pk, SymmetricCipher::Aes256Gcm).unwrap();
+	let bob_ratchet = Ratchet::respond(&shared_secret, (bob_ratchet_pk, bob_ratchet_sk), SymmetricCipher::XChaCha20Poly1305).unwrap();
+	let (salt_a, salt_b) = typed::derive_salts(&rand::thread_rng().gen::<[u8; 32]>(), &rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+	let (id_a, id_b) = (id_gen(), id_gen());
+	let mdc_seed = mdc_gen();
+	let timestamp = get_current_timestamp().unwrap();
+	let mut alice = Session::new(alice_ratchet.clone(), Some(alice_sign_sk), Some(bob_sign_pk.clone()), (salt_a.clone(), salt_b.clone()), (&id_a, &id_b), &mdc_seed, &timestamp).unwrap();
+	let mut bob = Session::new(bob_ratchet, Some(bob_sign_sk), Some(alice_sign_pk), (salt_b.clone(), salt_a.clone()), (&id_b, &id_a), &mdc_seed, &timestamp).unwrap();
+	
+	// a message signed with another key is rejected without changing the state of the receiver
+	let (_, mallory_sign_sk) = typed::sign_keygen(SignatureAlgorithm::MlDsa65);
+	let mut mallory = Session::new(alice_ratchet, Some(mallory_sign_sk), Some(bob_sign_pk), (salt_a, salt_b), (&id_a, &id_b), &mdc_seed, &timestamp).unwrap();
+	let forged = mallory.encrypt(b"forged").unwrap();
+	assert!(matches!(bob.decrypt(&forged), Err(DawnCryptoError::DecryptionFailed)));
+	assert!(matches!(debug::decrypt_session(&mut bob, &forged), Err(DawnCryptoError::SignatureInvalid(_))));
+	
+	for i in 0..3 {
+		let msg = format!("message {}", i);
+		assert_eq!(bob.decrypt(&alice.encrypt(msg.as_bytes()).unwrap()).unwrap().0, msg.as_bytes());
+		assert_eq!(alice.decrypt(&bob.encrypt(msg.as_bytes()).unwrap()).unwrap().0, msg.as_bytes());
+	}
+	
+	// lost and delayed messages don't break the session, unlike the PFS chain of decrypt_msg
+	let _lost = alice.encrypt(b"lost").unwrap();
+	let delayed = alice.encrypt(b"delayed").unwrap();
+	assert_eq!(bob.decrypt(&alice.encrypt(b"on time").unwrap()).unwrap().0, b"on time");
+	assert_eq!(alice.decrypt(&bob.encrypt(b"reply").unwrap()).unwrap().0, b"reply");
+	assert_eq!(bob.decrypt(&delayed).unwrap().0, b"delayed");
+	assert!(matches!(bob.decrypt(&delayed), Err(DawnCryptoError::DecryptionFailed)));
+	assert!(matches!(debug::decrypt_session(&mut bob, &delayed), Err(DawnCryptoError::Replay(_))));
+	assert_eq!(bob.decrypt(&alice.encrypt(b"next turn").unwrap()).unwrap().0, b"next turn");
+	
+	// both sides agree on the temporary ids and mdcs
+	assert_eq!(alice.remote_temp_id().unwrap(), bob.current_temp_id().unwrap());
+	assert_eq!(alice.current_temp_id().unwrap(), bob.remote_temp_id().unwrap());
+	assert_ne!(alice.current_temp_id().unwrap(), alice.remote_temp_id().unwrap());
+	let temp_id = alice.remote_temp_id().unwrap();
+	assert_eq!(alice.mdc(&temp_id).unwrap(), bob.mdc(&temp_id).unwrap());
+	
+	// a restored session continues where the original one stopped
+	let serialized = alice.to_bytes();
+	let mut alice = Session::from_bytes(&serialized).unwrap();
+	assert_eq!(&alice.to_bytes()[..], &serialized[..]);
+	assert_eq!(bob.decrypt(&alice.encrypt(b"restored").unwrap()).unwrap().0, b"restored");
+	
+	// rotating advances the id seeds once for every period, so both sides stay in sync
+	let old_timestamp = |session: &Session| {
+		let mut serialized = session.to_bytes().to_vec();
+		let len = serialized.len();
+		serialized[len - 9..].copy_from_slice(b"202301010");
+		Session::from_bytes(&serialized).unwrap()
+	};
+	let (mut alice, mut bob) = (old_timestamp(&alice), old_timestamp(&bob));
+	let old_temp_id = alice.current_temp_id().unwrap();
+	alice.rotate().unwrap();
+	bob.rotate().unwrap();
+	bob.rotate().unwrap();
+	assert_eq!(alice.id_timestamp(), get_current_timestamp().unwrap());
+	assert_ne!(alice.current_temp_id().unwrap(), old_temp_id);
+	assert_eq!(alice.current_temp_id().unwrap(), bob.remote_temp_id().unwrap());
+	assert_eq!(alice.remote_temp_id().unwrap(), bob.current_temp_id().unwrap());
+	
+	// invalid serialized sessions are rejected
+	let mut serialized = serialized.to_vec();
+	assert!(Session::from_bytes(&serialized[..serialized.len() - 1]).is_err());
+	serialized[1] = 42;
+	assert!(matches!(Session::from_bytes(&serialized), Err(DawnCryptoError::UnsupportedVersion(42))));
+}
+
+#[test]
+fn test_sealed_storage() {
+	let storage_key = sym_key_gen();
+	let identity = b"keys generated by init()";
+	let sealed = seal_record(RecordType::Identity, b"identity", identity, &storage_key, SymmetricCipher::XChaCha20Poly1305).unwrap();
+	assert_eq!(&open_record(RecordType::Identity, b"identity", &sealed, &storage_key).unwrap()[..], identity);
+	
+	// records can't be opened as another type, with another id or using another key
+	assert!(matches!(open_record(RecordType::Session, b"identity", &sealed, &storage_key), Err(DawnCryptoError::InvalidInput(_))));
+	assert!(matches!(open_record(RecordType::Identity, b"other", &sealed, &storage_key), Err(DawnCryptoError::SymmetricDecryption(_))));
+	assert!(matches!(open_record(RecordType::Identity, b"identity", &sealed, &sym_key_gen()), Err(DawnCryptoError::SymmetricDecryption(_))));
+	
+	// changing the type in the header doesn't help either
+	let mut modified = sealed.clone();
+	modified[2] = RecordType::Session.id();
+	assert!(matches!(open_record(RecordType::Session, b"identity", &modified, &storage_key), Err(DawnCryptoError::SymmetricDecryption(_))));
+	modified = sealed.clone();
+	modified[1] = 42;
+	assert!(matches!(open_record(RecordType::Identity, b"identity", &modified, &storage_key), Err(DawnCryptoError::UnsupportedVersion(42))));
+	
+	// sessions can be sealed directly
+	let ratchet = Ratchet::initiate(&sym_key_gen(), &hybrid_keygen().0, SymmetricCipher::Aes256Gcm).unwrap();
+	let salt = Salt::from_bytes(&rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+	let session = Session::new(ratchet, None, None, (salt.clone(), salt), (&id_gen(), &id_gen()), &mdc_gen(), &get_current_timestamp().unwrap()).unwrap();
+	let sealed = seal_session(&session, b"chat 1", &storage_key, SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(&open_session(b"chat 1", &sealed, &storage_key).unwrap().to_bytes()[..], &session.to_bytes()[..]);
+	assert!(open_session(b"chat 2", &sealed, &storage_key).is_err());
+}
+
+#[test]
+fn test_password_encryption() {
+	// low parameters to keep the test fast
+	let params = Argon2Params { memory_kib: 1024, iterations: 1, parallelism: 1 };
+	let data = b"local backup";
+	let encrypted = encrypt_with_password(data, b"correct horse", &params, SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(decrypt_with_password(&encrypted, b"correct horse").unwrap(), data);
+	assert!(matches!(decrypt_with_password(&encrypted, b"wrong horse"), Err(DawnCryptoError::SymmetricDecryption(_))));
+	
+	// the salt is part of the authenticated header
+	let mut modified = encrypted.clone();
+	modified[14] ^= 1;
+	assert!(matches!(decrypt_with_password(&modified, b"correct horse"), Err(DawnCryptoError::SymmetricDecryption(_))));
+	
+	// parameters above the limits are rejected before deriving anything
+	// (4 GiB and 100 iterations would take minutes if we started hashing)
+	let mut modified = encrypted.clone();
+	modified[2..6].copy_from_slice(&(4 * 1024 * 1024u32).to_be_bytes());
+	modified[6..10].copy_from_slice(&100u32.to_be_bytes());
+	assert!(matches!(decrypt_with_password(&modified, b"correct horse"), Err(DawnCryptoError::InvalidParameters)));
+	let mut modified = encrypted.clone();
+	modified[2..6].copy_from_slice(&(Argon2Params::MAX.memory_kib + 1).to_be_bytes());
+	assert!(matches!(decrypt_with_password(&modified, b"correct horse"), Err(DawnCryptoError::InvalidParameters)));
+	let mut modified = encrypted.clone();
+	modified[10..14].copy_from_slice(&(Argon2Params::MAX.parallelism + 1).to_be_bytes());
+	assert!(matches!(decrypt_with_password(&modified, b"correct horse"), Err(DawnCryptoError::InvalidParameters)));
+	assert!(matches!(password_key_gen(b"1234", &Argon2Params { iterations: Argon2Params::MAX.iterations + 1, ..params }), Err(DawnCryptoError::InvalidParameters)));
+	assert!(Argon2Params::default().within_limits());
+	
+	// keys can be derived again from the encoded parameters and salt
+	let (key, encoded_params) = password_key_gen(b"1234", &params).unwrap();
+	assert_eq!(get_password_key(b"1234", &encoded_params).unwrap(), key);
+	assert_ne!(get_password_key(b"1235", &encoded_params).unwrap(), key);
+	assert_ne!(password_key_gen(b"1234", &params).unwrap().0, key);
+	assert!(matches!(password_key_gen(b"1234", &Argon2Params { memory_kib: 1, iterations: 1, parallelism: 1 }), Err(DawnCryptoError::KeyDerivation(_))));
 }
 
 #[test]
@@ -81,19 +663,239 @@ fn test_mdc_gen() {
 
 #[test]
 fn test_predictable_mdc_gen() {
-	let predictable_mdc = predictable_mdc_gen("42", "tempid");
+	let predictable_mdc = predictable_mdc_gen("42", "tempid").unwrap();
 	let mdc_regex = Regex::new("^[0-9a-f]{8}$").unwrap();
 	assert!(mdc_regex.is_match(&predictable_mdc));
 }
 
+#[test]
+fn test_key_schedule() {
+	let kyber_secret = rand::thread_rng().gen::<[u8; 32]>();
+	let curve_secret = rand::thread_rng().gen::<[u8; 32]>();
+	
+	// the legacy functions keep deriving the same values as before
+	let (salt1, salt2) = derive_salts_legacy(&kyber_secret, &curve_secret).unwrap();
+	assert_eq!(&salt1[..], &hash(&[kyber_secret, curve_secret].concat())[..]);
+	assert_eq!(&salt2[..], &hash(&[curve_secret, kyber_secret].concat())[..]);
+	assert_eq!(predictable_mdc_gen_legacy("42", "tempid"), hex::encode(hash(b"42tempid"))[..8]);
+	let id = id_gen();
+	let salt = hex::encode(&salt1);
+	assert_eq!(get_next_id_legacy(&id, &salt).unwrap(), hex::encode(hash(&[id.as_bytes(), salt.as_bytes()].concat())));
+	
+	// the key schedule derives different values for every purpose
+	let (new_salt1, new_salt2) = derive_salts(&kyber_secret, &curve_secret).unwrap();
+	assert_ne!(new_salt1, new_salt2);
+	assert_ne!(&new_salt1[..], &salt1[..]);
+	assert_ne!(get_next_id(&id, &salt).unwrap(), get_next_id_legacy(&id, &salt).unwrap());
+	assert_ne!(predictable_mdc_gen("42", "tempid").unwrap(), predictable_mdc_gen_legacy("42", "tempid"));
+	assert_ne!(predictable_mdc_gen("42", "tempid").unwrap(), predictable_mdc_gen("42t", "empid").unwrap());
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	assert_ne!(kdf::get_pfs_key(&pfs_key, &salt2).unwrap(), kdf::get_pfs_key_legacy(&pfs_key, &salt2));
+	assert_ne!(kdf::get_pfs_key(&pfs_key, &[0u8; 32]).unwrap(), kdf::message_key(&pfs_key, &[]).unwrap());
+	
+	// messages use the key schedule
+	let (pk, sk) = kyber_keygen();
+	let (enc_msg, new_key) = encrypt_msg(&pk, None, &pfs_key, &salt2, "key schedule", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(enc_msg[1], envelope::VERSION);
+	assert_eq!(new_key, kdf::get_pfs_key(&pfs_key, &salt2).unwrap());
+	assert_eq!(decrypt_msg(&sk, None, &pfs_key, &salt2, &enc_msg).unwrap().0, "key schedule");
+}
+
+#[test]
+fn test_legacy_cbc_migration() {
+	use openssl::symm::{encrypt as cbc_encrypt, Cipher};
+	let legacy_encrypt = |data: &[u8], key: &[u8]| {
+		let iv = rand::thread_rng().gen::<[u8; 16]>();
+		[iv.to_vec(), cbc_encrypt(Cipher::aes_256_cbc(), key, Some(&iv), data).unwrap()].concat()
+	};
+	
+	// data encrypted by earlier versions of encrypt_data
+	let key = sym_key_gen();
+	let legacy_data = legacy_encrypt(b"stored for months", &key);
+	assert!(matches!(decrypt_data(&legacy_data, &key, SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::DecryptionFailed)));
+	assert_eq!(decrypt_data_compat(&legacy_data, &key, SymmetricCipher::Aes256Gcm).unwrap(), (b"stored for months".to_vec(), warning::LEGACY_FORMAT));
+	let upgraded = reencrypt_legacy(&legacy_data, &key, SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(decrypt_data(&upgraded, &key, SymmetricCipher::Aes256Gcm).unwrap(), b"stored for months");
+	assert_eq!(decrypt_data_compat(&upgraded, &key, SymmetricCipher::Aes256Gcm).unwrap().1, warning::NONE);
+	assert_eq!(reencrypt_legacy(&upgraded, &key, SymmetricCipher::Aes256Gcm).unwrap(), upgraded);
+	
+	// messages encrypted by earlier versions of encrypt_msg
+	let (pk, sk) = kyber_keygen();
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::SphincsShake192f);
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	let legacy_msg = |signature: &[u8], msg: &str| {
+		let (kyber_shared_secret, kyber_ciphertext) = kyber::get_shared_secret(&pk).unwrap();
+		let secret = kdf::message_key_legacy(&kyber_shared_secret, &kdf::get_pfs_key_legacy(&pfs_key, &salt));
+		let payload = format!("{}.{}", hex::encode(signature), msg);
+		[kyber_ciphertext, legacy_encrypt(payload.as_bytes(), &secret)].concat()
+	};
+	let (dec_msg, new_pfs_key, warning) = decrypt_msg_compat(&sk, None, &pfs_key, &salt, &legacy_msg(&[], "unsigned")).unwrap();
+	assert_eq!(dec_msg, "unsigned");
+	assert_eq!(new_pfs_key, kdf::get_pfs_key_legacy(&pfs_key, &salt));
+	assert_eq!(warning, warning::LEGACY_FORMAT | warning::NO_SIGNATURE);
+	assert!(decrypt_msg(&sk, None, &pfs_key, &salt, &legacy_msg(&[], "unsigned")).is_err());
+	
+	// a legacy signature only covers the message, so whoever received it could forward it to us in the legacy format
+	let signature = sign::sign(&sign_sk, b"old message").unwrap();
+	let forwarded = legacy_msg(&signature, "old message");
+	assert!(matches!(decrypt_msg_compat(&sk, Some(&sign_pk), &pfs_key, &salt, &forwarded), Err(DawnCryptoError::DecryptionFailed)));
+	assert!(matches!(debug::decrypt_msg_compat(&sk, Some(&sign_pk), &pfs_key, &salt, &forwarded), Err(DawnCryptoError::SignatureInvalid(_))));
+	assert_eq!(decrypt_msg_compat(&sk, None, &pfs_key, &salt, &forwarded).unwrap().2, warning::LEGACY_FORMAT);
+	
+	// current messages decrypt without warning
+	let (enc_msg, _) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "new message", SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap().2, warning::NONE);
+	assert_eq!(decrypt_msg_compat(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap().2, warning::NONE);
+}
+
 #[test]
 fn test_data_encryption() {
 	let key = sym_key_gen();
 	let data = vec![0,0,42,42];
-	let ciphertext = encrypt_data(&data, &key).unwrap();
-	assert_ne!(ciphertext, data);
-	let dec_data = decrypt_data(&ciphertext, &key).unwrap();
-	assert_eq!(data, dec_data);
+	for cipher in [SymmetricCipher::Aes256Gcm, SymmetricCipher::XChaCha20Poly1305] {
+		let ciphertext = encrypt_data(&data, &key, cipher).unwrap();
+		assert_ne!(ciphertext, data);
+		let dec_data = decrypt_data(&ciphertext, &key, cipher).unwrap();
+		assert_eq!(data, dec_data);
+	}
+	
+	// decrypting with the wrong cipher needs to fail
+	let ciphertext = encrypt_data(&data, &key, SymmetricCipher::XChaCha20Poly1305).unwrap();
+	assert!(decrypt_data(&ciphertext, &key, SymmetricCipher::Aes256Gcm).is_err());
+	
+	// every tampered ciphertext is rejected, also when it looks like the legacy format
+	let ciphertext = encrypt_data(&[42; 20], &key, SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(ciphertext.len(), 48);
+	for i in 0..ciphertext.len() {
+		let mut modified = ciphertext.clone();
+		modified[i] ^= 1;
+		assert!(matches!(decrypt_data(&modified, &key, SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::DecryptionFailed)));
+	}
+}
+
+#[test]
+fn test_data_aead_encryption() {
+	let key = sym_key_gen();
+	let data = vec![0,0,42,42];
+	for cipher in [SymmetricCipher::Aes256Gcm, SymmetricCipher::XChaCha20Poly1305] {
+		let ciphertext = encrypt_data_aead(&data, &key, Some(b"file"), cipher).unwrap();
+		assert_eq!(decrypt_data_aead(&ciphertext, &key, Some(b"file"), cipher).unwrap(), data);
+		assert!(decrypt_data_aead(&ciphertext, &key, Some(b"other file"), cipher).is_err());
+		assert!(decrypt_data_aead(&ciphertext, &key, None, cipher).is_err());
+		
+		// flipping any bit of the ciphertext needs to be detected
+		for i in 0..ciphertext.len() {
+			let mut tampered = ciphertext.clone();
+			tampered[i] ^= 1;
+			assert!(decrypt_data_aead(&tampered, &key, Some(b"file"), cipher).is_err());
+		}
+		
+		let ciphertext = encrypt_data_aead(&[], &key, None, cipher).unwrap();
+		assert_eq!(decrypt_data_aead(&ciphertext, &key, None, cipher).unwrap(), Vec::<u8>::new());
+	}
+}
+
+#[test]
+fn test_padding() {
+	assert_eq!(Padding::None.padded_len(10), 11);
+	assert_eq!(Padding::Padme.padded_len(1000), 1024);
+	assert_eq!(Padding::Padme.padded_len(1010), 1024);
+	assert_eq!(Padding::PowerOfTwo { max_overhead: 1024 }.padded_len(100), 128);
+	assert_eq!(Padding::PowerOfTwo { max_overhead: 1024 }.padded_len(5000), 5120);
+	assert_eq!(padding::unpad(&padding::pad(b"\x80\0", Padding::Padme)).unwrap(), b"\x80\0");
+	assert!(padding::unpad(&[42, 0, 0]).is_none());
+	assert!(padding::unpad(&[0, 0]).is_none());
+	
+	// attachments of similar size can't be told apart
+	let key = sym_key_gen();
+	let padding = Padding::PowerOfTwo { max_overhead: 1024 };
+	let ciphertext = encrypt_data_padded(&[42; 100], &key, padding, SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(ciphertext.len(), encrypt_data_padded(&[42; 120], &key, padding, SymmetricCipher::Aes256Gcm).unwrap().len());
+	assert_eq!(decrypt_data_padded(&ciphertext, &key, SymmetricCipher::Aes256Gcm).unwrap(), vec![42; 100]);
+	let unpadded = encrypt_data(&[42; 100], &key, SymmetricCipher::Aes256Gcm).unwrap();
+	assert!(decrypt_data_padded(&unpadded, &key, SymmetricCipher::Aes256Gcm).is_err());
+	
+	// neither can messages
+	let (pk, sk) = kyber_keygen();
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	let (short_msg, _) = encrypt_msg_bytes_padded(&pk, None, &pfs_key, &salt, b"ok", padding, SymmetricCipher::Aes256Gcm).unwrap();
+	let (long_msg, _) = encrypt_msg_bytes_padded(&pk, None, &pfs_key, &salt, b"see you", padding, SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(short_msg.len(), long_msg.len());
+	assert_eq!(decrypt_msg_bytes(&sk, None, &pfs_key, &salt, &short_msg).unwrap().0, b"ok");
+	let (enc_msg, _) = encrypt_msg(&pk, None, &pfs_key, &salt, &"a".repeat(1000), SymmetricCipher::Aes256Gcm).unwrap();
+	assert_eq!(enc_msg.len(), encrypt_msg(&pk, None, &pfs_key, &salt, &"a".repeat(1010), SymmetricCipher::Aes256Gcm).unwrap().0.len());
+	
+	// ratchet messages are padded as well
+	let (ratchet_pk, ratchet_sk) = hybrid_keygen();
+	let mut alice = Ratchet::initiate(&pfs_key, &ratchet_pk, SymmetricCipher::Aes256Gcm).unwrap();
+	let mut bob = Ratchet::respond(&pfs_key, (ratchet_pk, ratchet_sk), SymmetricCipher::Aes256Gcm).unwrap();
+	let short_msg = alice.encrypt_padded(b"good night", padding).unwrap();
+	let long_msg = alice.encrypt_padded(b"see you soon", padding).unwrap();
+	assert_eq!(short_msg.len(), long_msg.len());
+	assert_eq!(alice.encrypt(&[b'a'; 1000]).unwrap().len(), alice.encrypt(&[b'a'; 1010]).unwrap().len());
+	assert_eq!(bob.decrypt(&short_msg).unwrap(), b"good night");
+	assert_eq!(bob.decrypt(&long_msg).unwrap(), b"see you soon");
+	
+	// invalid padding is rejected after authentication
+	let (kyber_shared_secret, kyber_ciphertext) = kyber::get_shared_secret(&pk).unwrap();
+	let secret = kdf::message_key(&kyber_shared_secret, &kdf::get_pfs_key(&pfs_key, &salt).unwrap()).unwrap();
+	let mut envelope = MessageEnvelope { version: envelope::VERSION, kem: 1, cipher: SymmetricCipher::Aes256Gcm, kem_ciphertext: kyber_ciphertext, payload: vec![] };
+	envelope.payload = symm::encrypt_aead(&envelope::encode_payload(&[], b"unpadded"), &secret, &envelope.associated_data(), envelope.cipher).unwrap();
+	assert!(matches!(debug::decrypt_msg(&sk, None, &pfs_key, &salt, &envelope.encode()), Err(DawnCryptoError::InvalidMessage("invalid padding"))));
+}
+
+#[test]
+fn test_convergent_encryption() {
+	let group_secret = sym_key_gen();
+	let other_secret = sym_key_gen();
+	let meme = b"the same meme in 50 chats".repeat(100);
+	for cipher in [SymmetricCipher::Aes256Gcm, SymmetricCipher::XChaCha20Poly1305] {
+		// identical uploads of the group can be deduplicated
+		let (ciphertext, key) = encrypt_data_convergent(&meme, &group_secret, cipher).unwrap();
+		let (other_ciphertext, other_key) = encrypt_data_convergent(&meme, &group_secret, cipher).unwrap();
+		assert_eq!(ciphertext, other_ciphertext);
+		assert_eq!(key, other_key);
+		assert_eq!(key, convergent_key(&meme, &group_secret).unwrap());
+		assert_eq!(decrypt_data_convergent(&ciphertext, &key, cipher).unwrap(), meme);
+		
+		// other content and other secrets result in unrelated ciphertexts
+		assert_ne!(encrypt_data_convergent(b"another meme", &group_secret, cipher).unwrap().0[..24], ciphertext[..24]);
+		let (other_ciphertext, other_key) = encrypt_data_convergent(&meme, &other_secret, cipher).unwrap();
+		assert_ne!(other_ciphertext, ciphertext);
+		assert_ne!(other_key, key);
+		assert!(decrypt_data_convergent(&ciphertext, &other_key, cipher).is_err());
+		
+		// the synthetic nonce is checked, so a ciphertext with another nonce is rejected even if it authenticates
+		let nonce_len = cipher.nonce_len();
+		let mut modified = ciphertext.clone();
+		modified[0] ^= 1;
+		assert!(decrypt_data_convergent(&modified, &key, cipher).is_err());
+		let mut reused = vec![0u8; nonce_len];
+		reused.append(&mut symm::seal(b"other content", &kdf::siv_key(&key).unwrap(), &vec![0u8; nonce_len], &[], cipher).unwrap());
+		assert!(matches!(decrypt_data_convergent(&reused, &key, cipher), Err(DawnCryptoError::SymmetricDecryption(_))));
+		
+		// deterministic encryption binds the associated data
+		let ciphertext = encrypt_data_deterministic(b"data", &key, Some(b"file"), cipher).unwrap();
+		assert_eq!(ciphertext, encrypt_data_deterministic(b"data", &key, Some(b"file"), cipher).unwrap());
+		assert_ne!(ciphertext, encrypt_data_deterministic(b"data", &key, None, cipher).unwrap());
+		assert_eq!(decrypt_data_deterministic(&ciphertext, &key, Some(b"file"), cipher).unwrap(), b"data");
+		assert!(decrypt_data_deterministic(&ciphertext, &key, Some(b"other file"), cipher).is_err());
+	}
+	assert!(matches!(encrypt_data_convergent(&meme, &[0u8; 16], SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: 16 })));
+}
+
+#[test]
+fn test_tampered_message() {
+	let (pk, sk) = kyber_keygen();
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	let (enc_msg, _) = encrypt_msg(&pk, None, &pfs_key, &salt, "testing tampering", SymmetricCipher::Aes256Gcm).unwrap();
+	let mut tampered = enc_msg.clone();
+	let last = tampered.len() - 20;
+	tampered[last] ^= 1;
+	assert!(matches!(debug::decrypt_msg(&sk, None, &pfs_key, &salt, &tampered), Err(DawnCryptoError::SymmetricDecryption(_))));
 }
 
 #[test]
@@ -110,7 +912,7 @@ fn test_get_temp_id() {
 	let modifier = date_modifier + &time_modifier.to_string();
 	let human_readable_utc = c_time.date_naive().format("%d.%m.%Y").to_string() + " " + &c_time.time().format("%H:%M:%S").to_string();
 	println!("\n[test_get_temp_id] UTC: {}\n[test_get_temp_id] modifier string: {}\n", human_readable_utc, modifier);
-	assert_eq!(get_temp_id(&id), get_custom_temp_id(&id, &modifier));
+	assert_eq!(get_temp_id(&id).unwrap(), get_custom_temp_id(&id, &modifier).unwrap());
 }
 
 #[test]
@@ -134,10 +936,232 @@ fn test_get_next_id() {
 #[test]
 fn test_get_curve_secret() {
 	let (curve_pk, curve_sk) = curve_keygen();
-	assert!(get_curve_secret(&curve_sk, &vec![]).is_err());
+	assert!(get_curve_secret(&curve_sk, &[]).is_err());
+}
+
+#[test]
+fn test_secret_bytes() {
+	let key = sym_key_gen();
+	assert_eq!(key.len(), 32);
+	assert_eq!(format!("{:?}", key), "SecretBytes(<32 bytes redacted>)");
+	assert_eq!(key.clone(), key);
+	assert_ne!(sym_key_gen(), key);
+	assert_eq!(SecretBytes::concat(&[&key[..16], &key[16..]]), key);
+}
+
+#[test]
+fn test_typed_api() {
+	let (pk, sk) = typed::kem_keygen(KemAlgorithm::MlKem768);
+	let (sign_pk, sign_sk) = typed::sign_keygen(SignatureAlgorithm::MlDsa65);
+	let pfs_key = PfsKey::from_bytes(&rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+	let salt = Salt::from_bytes(&rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+	assert_eq!(sk.public_key().unwrap(), pk);
+	assert_eq!(pk.algorithm(), KemAlgorithm::MlKem768);
+	assert_eq!(sign_sk.algorithm(), SignatureAlgorithm::MlDsa65);
+	
+	let (enc_msg, new_key) = typed::encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "typed keys", SymmetricCipher::Aes256Gcm).unwrap();
+	let (dec_msg, other_new_key, warning) = typed::decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap();
+	assert_eq!(dec_msg, "typed keys");
+	assert_eq!(new_key, other_new_key);
+	assert_eq!(warning, warning::NONE);
+	
+	// the typed API is compatible with the one taking bytes
+	let (dec_msg, _, _) = decrypt_msg(sk.as_bytes(), Some(sign_pk.as_bytes()), pfs_key.as_bytes(), salt.as_bytes(), &enc_msg).unwrap();
+	assert_eq!(dec_msg, "typed keys");
+	
+	let key = typed::sym_key_gen();
+	let ciphertext = typed::encrypt_data(b"typed data", &key, SymmetricCipher::XChaCha20Poly1305).unwrap();
+	assert_eq!(typed::decrypt_data(&ciphertext, &key, SymmetricCipher::XChaCha20Poly1305).unwrap(), b"typed data");
+	
+	let (curve_pk, curve_sk) = typed::curve_keygen();
+	let (other_curve_pk, other_curve_sk) = typed::curve_keygen();
+	assert_eq!(typed::get_curve_secret(&curve_sk, &other_curve_pk).unwrap(), typed::get_curve_secret(&other_curve_sk, &curve_pk).unwrap());
+	
+	// keys get validated when constructed from bytes
+	assert!(KyberPublicKey::from_bytes(sk.as_bytes()).is_err());
+	assert!(KyberSecretKey::from_bytes(pk.as_bytes()).is_err());
+	assert!(VerifyingKey::from_bytes(sign_sk.as_bytes()).is_err());
+	assert!(SigningKey::from_bytes(sign_pk.as_bytes()).is_err());
+	assert!(SigningKey::from_bytes(sk.as_bytes()).is_err());
+	assert!(matches!(PfsKey::from_bytes(&[0u8; 16]), Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: 16 })));
+	assert!(CurvePublicKey::from_bytes(&[0u8; 33]).is_err());
+	assert!(KyberPublicKey::from_bytes(&kyber_keygen().0).is_ok());
+	
+	// secret keys don't show up in debug output
+	assert!(format!("{:?}", sign_sk).contains("redacted"));
+}
+
+#[test]
+fn test_error_kinds() {
+	use std::error::Error;
+	let (pk, sk) = kyber_keygen();
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	assert!(matches!(encrypt_msg(&pk, None, &pfs_key[..16], &salt, "", SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: 16 })));
+	assert!(matches!(encrypt_msg(&pk, None, &pfs_key, &salt[..16], "", SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::InvalidSalt)));
+	assert!(matches!(encrypt_msg(&sk, None, &pfs_key, &salt, "", SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::InvalidKeyLength { .. } | DawnCryptoError::UnsupportedAlgorithm(_))));
+	assert!(matches!(encrypt_msg_hybrid(&sk, None, &pfs_key, &salt, "", SymmetricCipher::Aes256Gcm), Err(DawnCryptoError::KemEncapsulation(_))));
+	assert!(matches!(get_temp_id("wrong id"), Err(DawnCryptoError::InvalidId)));
+	assert!(matches!(get_all_timestamps_since("2023"), Err(DawnCryptoError::InvalidTimestamp(_))));
+	
+	// the error of the underlying library is available as source
+	let err = decrypt_data_aead(&[0u8; 60], &sym_key_gen(), None, SymmetricCipher::Aes256Gcm).unwrap_err();
+	assert!(matches!(err, DawnCryptoError::SymmetricDecryption(_)));
+	assert!(err.source().is_some());
+	assert_eq!(err.to_string(), "symmetric decryption failed");
+}
+
+#[test]
+fn test_opaque_decryption_errors() {
+	use std::error::Error;
+	let (pk, sk) = kyber_keygen();
+	let (sign_pk, sign_sk) = sign_keygen(SignatureAlgorithm::MlDsa65);
+	let (other_sign_pk, _) = sign_keygen(SignatureAlgorithm::MlDsa65);
+	let pfs_key = rand::thread_rng().gen::<[u8; 32]>();
+	let salt = rand::thread_rng().gen::<[u8;32]>();
+	let (enc_msg, _) = encrypt_msg(&pk, Some(&sign_sk), &pfs_key, &salt, "opaque", SymmetricCipher::Aes256Gcm).unwrap();
+	
+	let mut tampered = enc_msg.clone();
+	let len = tampered.len();
+	tampered[len - 1] ^= 1;
+	let mut unknown_version = enc_msg.clone();
+	unknown_version[1] = 42;
+	let (other_pk, _) = kem_keygen(KemAlgorithm::MlKem768);
+	let (other_kem, _) = encrypt_msg(&other_pk, None, &pfs_key, &salt, "opaque", SymmetricCipher::Aes256Gcm).unwrap();
+	let (binary, _) = encrypt_msg_bytes(&pk, None, &pfs_key, &salt, &[0xff], SymmetricCipher::Aes256Gcm).unwrap();
+	
+	// every failure caused by the message looks the same, the details are only available through the debug functions
+	for (enc_msg, verifying_key) in [(&tampered, None), (&unknown_version, None), (&other_kem, None), (&binary, None), (&enc_msg, Some(&other_sign_pk)), (&enc_msg[..10].to_vec(), None)] {
+		let err = decrypt_msg(&sk, verifying_key.map(|key| &key[..]), &pfs_key, &salt, enc_msg).unwrap_err();
+		assert!(matches!(err, DawnCryptoError::DecryptionFailed));
+		assert!(err.source().is_none());
+		assert!(!matches!(debug::decrypt_msg(&sk, verifying_key.map(|key| &key[..]), &pfs_key, &salt, enc_msg), Err(DawnCryptoError::DecryptionFailed) | Ok(_)));
+	}
+	assert!(matches!(debug::decrypt_msg(&sk, None, &pfs_key, &salt, &tampered), Err(DawnCryptoError::SymmetricDecryption(_))));
+	assert!(matches!(debug::decrypt_msg(&sk, Some(&other_sign_pk), &pfs_key, &salt, &enc_msg), Err(DawnCryptoError::SignatureInvalid(_))));
+	
+	// invalid own inputs are still reported in detail
+	assert!(matches!(decrypt_msg(&sk, None, &pfs_key[..16], &salt, &enc_msg), Err(DawnCryptoError::InvalidKeyLength { expected: 32, actual: 16 })));
+	assert!(matches!(decrypt_msg(&sk, None, &pfs_key, &salt[..16], &enc_msg), Err(DawnCryptoError::InvalidSalt)));
+	assert_eq!(decrypt_msg(&sk, Some(&sign_pk), &pfs_key, &salt, &enc_msg).unwrap().0, "opaque");
 }
 
 #[test]
 fn test_get_all_timestamps_since() {
 	println!("{:?}", get_all_timestamps_since("202308212"));
 }
+
+#[test]
+fn test_stream_encryption() {
+	use std::io::{Read, Write};
+	let key = sym_key_gen();
+	for cipher in [SymmetricCipher::Aes256Gcm, SymmetricCipher::XChaCha20Poly1305] {
+		for len in [0, 1, 15, 16, 17, 48, 100] {
+			let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+			let mut encryptor = StreamEncryptor::with_chunk_size(Vec::new(), &key, cipher, 16).unwrap();
+			// write in pieces not aligned to the chunk size
+			for piece in data.chunks(7) {
+				encryptor.write_all(piece).unwrap();
+			}
+			let ciphertext = encryptor.finish().unwrap();
+			
+			let mut decryptor = StreamDecryptor::new(&ciphertext[..], &key).unwrap();
+			let mut dec_data = vec![];
+			decryptor.read_to_end(&mut dec_data).unwrap();
+			assert_eq!(dec_data, data);
+		}
+	}
+	
+	// wrong key
+	let mut encryptor = StreamEncryptor::new(Vec::new(), &key, SymmetricCipher::Aes256Gcm).unwrap();
+	encryptor.write_all(b"stream").unwrap();
+	let ciphertext = encryptor.finish().unwrap();
+	let mut decryptor = StreamDecryptor::new(&ciphertext[..], &sym_key_gen()).unwrap();
+	assert!(decryptor.read_to_end(&mut vec![]).is_err());
+}
+
+#[test]
+fn test_stream_manipulation() {
+	use std::io::{Read, Write};
+	let key = sym_key_gen();
+	let data = [42u8; 64];
+	let mut encryptor = StreamEncryptor::with_chunk_size(Vec::new(), &key, SymmetricCipher::Aes256Gcm, 16).unwrap();
+	encryptor.write_all(&data).unwrap();
+	let ciphertext = encryptor.finish().unwrap();
+	
+	// header (38 bytes) followed by four chunks of 32 bytes each
+	let header = &ciphertext[..38];
+	let chunks: Vec<&[u8]> = ciphertext[38..].chunks(32).collect();
+	assert_eq!(chunks.len(), 4);
+	let decrypt = |stream: Vec<u8>| {
+		let mut dec_data = vec![];
+		StreamDecryptor::new(&stream[..], &key)?.read_to_end(&mut dec_data).map(|_| dec_data)
+	};
+	assert_eq!(decrypt(ciphertext.clone()).unwrap(), data);
+	
+	// reordered chunks
+	assert!(decrypt([header, chunks[1], chunks[0], chunks[2], chunks[3]].concat()).is_err());
+	// truncated at a chunk boundary
+	assert!(decrypt([header, chunks[0], chunks[1], chunks[2]].concat()).is_err());
+	// truncated inside a chunk
+	assert!(decrypt(ciphertext[..ciphertext.len() - 1].to_vec()).is_err());
+	// extended by a duplicated chunk or by arbitrary data
+	assert!(decrypt([header, chunks[0], chunks[1], chunks[2], chunks[3], chunks[3]].concat()).is_err());
+	assert!(decrypt([&ciphertext[..], &[0u8]].concat()).is_err());
+	// modified chunk size in the header
+	let mut modified = ciphertext.clone();
+	modified[5] = 15;
+	assert!(decrypt(modified).is_err());
+}
+
+#[test]
+fn test_container_random_access() {
+	use std::cell::Cell;
+	let key = sym_key_gen();
+	let data: Vec<u8> = (0..100u32).map(|i| i as u8).collect();
+	let mut container = vec![];
+	encrypt_container_with_chunk_size(&data[..], data.len() as u64, &mut container, &key, SymmetricCipher::XChaCha20Poly1305, 16).unwrap();
+	
+	// keep track of how much ciphertext gets fetched
+	let fetched = Cell::new(0);
+	let fetch = |offset: u64, len: usize| {
+		fetched.set(fetched.get() + len);
+		let offset = offset as usize;
+		Ok(container[offset..(offset + len).min(container.len())].to_vec())
+	};
+	let mut reader = ContainerReader::open(&key, fetch).unwrap();
+	assert_eq!(reader.len(), 100);
+	for (start, end) in [(0, 100), (0, 1), (15, 17), (16, 32), (40, 41), (99, 100), (50, 50)] {
+		assert_eq!(reader.read_range(start..end).unwrap(), data[start as usize..end as usize]);
+	}
+	assert!(reader.read_range(90..101).is_err());
+	
+	// reading a few bytes only fetches the chunk containing them
+	fetched.set(0);
+	reader.read_range(40..41).unwrap();
+	assert_eq!(fetched.get(), 16 + 16);
+	
+	// a modified chunk is only detected when it is read
+	let mut modified = container.clone();
+	modified[46 + 32 + 1] ^= 1;
+	let mut reader = ContainerReader::open(&key, |offset, len| Ok(modified[offset as usize..offset as usize + len].to_vec())).unwrap();
+	assert!(reader.read_range(0..16).is_ok());
+	assert!(reader.read_range(16..17).is_err());
+	
+	// a modified header (e.g. a shortened length) makes every chunk fail
+	let mut modified = container.clone();
+	modified[13] = 90;
+	let mut reader = ContainerReader::open(&key, |offset, len| Ok(modified[offset as usize..offset as usize + len].to_vec())).unwrap();
+	assert!(reader.read_range(0..1).is_err());
+	assert!(reader.read_range(80..90).is_err());
+	
+	// a manipulated length can't make the positions of the chunks overflow
+	let mut modified = container.clone();
+	modified[6..14].copy_from_slice(&u64::MAX.to_be_bytes());
+	let mut reader = ContainerReader::open(&key, |offset, len| Ok(modified[offset as usize..offset as usize + len].to_vec())).unwrap();
+	assert_eq!(reader.read_range(u64::MAX - 1..u64::MAX).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+	
+	// the announced length has to match the data
+	assert!(encrypt_container(&data[..], 99, &mut vec![], &key, SymmetricCipher::Aes256Gcm).is_err());
+	assert!(encrypt_container(&data[..], 101, &mut vec![], &key, SymmetricCipher::Aes256Gcm).is_err());
+}
@@ -0,0 +1,153 @@
+/*	Copyright (c) 2022, 2023 Laurenz Werner
+	
+	This file is part of Dawn.
+	
+	Dawn is free software: you can redistribute it and/or modify
+	it under the terms of the GNU General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+	
+	Dawn is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU General Public License for more details.
+	
+	You should have received a copy of the GNU General Public License
+	along with Dawn.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+// versions of the public API taking typed keys instead of byte slices
+// they behave exactly like the functions of the same name in the crate root
+
+use crate::*;
+
+pub fn kyber_keygen() -> (KyberPublicKey, KyberSecretKey) {
+	let (public_key, secret_key) = crate::kyber_keygen();
+	(KyberPublicKey::new(public_key), KyberSecretKey::new(secret_key))
+}
+
+pub fn kem_keygen(algorithm: KemAlgorithm) -> (KyberPublicKey, KyberSecretKey) {
+	let (public_key, secret_key) = crate::kem_keygen(algorithm);
+	(KyberPublicKey::new(public_key), KyberSecretKey::new(secret_key))
+}
+
+pub fn sign_keygen(algorithm: SignatureAlgorithm) -> (VerifyingKey, SigningKey) {
+	let (public_key, secret_key) = crate::sign_keygen(algorithm);
+	(VerifyingKey::new(public_key), SigningKey::new(secret_key))
+}
+
+pub fn curve_keygen() -> (CurvePublicKey, CurveSecretKey) {
+	let (public_key, secret_key) = crate::curve_keygen();
+	(CurvePublicKey::new(public_key), CurveSecretKey::new(secret_key))
+}
+
+pub fn sym_key_gen() -> SymmetricKey {
+	SymmetricKey::new(crate::sym_key_gen())
+}
+
+pub fn get_curve_secret(secret_key: &CurveSecretKey, public_key: &CurvePublicKey) -> Result<SecretBytes, DawnCryptoError> {
+	crate::get_curve_secret(secret_key.as_bytes(), public_key.as_bytes())
+}
+
+pub fn get_kyber_secret(public_key: &KyberPublicKey) -> Result<(SecretBytes, Vec<u8>), DawnCryptoError> {
+	crate::get_kyber_secret(public_key.as_bytes())
+}
+
+pub fn decrypt_kyber_secret(ciphertext: &[u8], secret_key: &KyberSecretKey) -> Result<SecretBytes, DawnCryptoError> {
+	crate::decrypt_kyber_secret(ciphertext, secret_key.as_bytes())
+}
+
+pub fn derive_salts(kyber_secret: &[u8], curve_secret: &[u8]) -> Result<(Salt, Salt), DawnCryptoError> {
+	let (salt1, salt2) = crate::derive_salts(kyber_secret, curve_secret)?;
+	Ok((Salt::new(salt1), Salt::new(salt2)))
+}
+
+pub fn derive_salts_legacy(kyber_secret: &[u8], curve_secret: &[u8]) -> Result<(Salt, Salt), DawnCryptoError> {
+	let (salt1, salt2) = crate::derive_salts_legacy(kyber_secret, curve_secret)?;
+	Ok((Salt::new(salt1), Salt::new(salt2)))
+}
+
+pub fn encrypt_msg(pub_key: &KyberPublicKey, sec_key: Option<&SigningKey>, pfs_key: &PfsKey, salt: &Salt, msg: &str, cipher: SymmetricCipher) -> Result<(Vec<u8>, PfsKey), DawnCryptoError> {
+	encrypt_msg_bytes(pub_key, sec_key, pfs_key, salt, msg.as_bytes(), cipher)
+}
+
+pub fn decrypt_msg(sec_key: &KyberSecretKey, pub_key: Option<&VerifyingKey>, pfs_key: &PfsKey, salt: &Salt, enc_msg: &[u8]) -> Result<(String, PfsKey, u8), DawnCryptoError> {
+	let (message, new_pfs_key, warning) = crate::decrypt_msg(sec_key.as_bytes(), pub_key.map(VerifyingKey::as_bytes), pfs_key.as_bytes(), salt.as_bytes(), enc_msg)?;
+	Ok((message, PfsKey::new(new_pfs_key), warning))
+}
+
+pub fn encrypt_msg_bytes(pub_key: &KyberPublicKey, sec_key: Option<&SigningKey>, pfs_key: &PfsKey, salt: &Salt, msg: &[u8], cipher: SymmetricCipher) -> Result<(Vec<u8>, PfsKey), DawnCryptoError> {
+	let (enc_msg, new_pfs_key) = crate::encrypt_msg_bytes(pub_key.as_bytes(), sec_key.map(SigningKey::as_bytes), pfs_key.as_bytes(), salt.as_bytes(), msg, cipher)?;
+	Ok((enc_msg, PfsKey::new(new_pfs_key)))
+}
+
+pub fn decrypt_msg_bytes(sec_key: &KyberSecretKey, pub_key: Option<&VerifyingKey>, pfs_key: &PfsKey, salt: &Salt, enc_msg: &[u8]) -> Result<(Vec<u8>, PfsKey, u8), DawnCryptoError> {
+	let (message, new_pfs_key, warning) = crate::decrypt_msg_bytes(sec_key.as_bytes(), pub_key.map(VerifyingKey::as_bytes), pfs_key.as_bytes(), salt.as_bytes(), enc_msg)?;
+	Ok((message, PfsKey::new(new_pfs_key), warning))
+}
+
+pub fn encrypt_data(data: &[u8], key: &SymmetricKey, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::encrypt_data(data, key.as_bytes(), cipher)
+}
+
+pub fn decrypt_data(encrypted_data: &[u8], key: &SymmetricKey, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::decrypt_data(encrypted_data, key.as_bytes(), cipher)
+}
+
+pub fn encrypt_data_padded(data: &[u8], key: &SymmetricKey, padding: Padding, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::encrypt_data_padded(data, key.as_bytes(), padding, cipher)
+}
+
+pub fn decrypt_data_padded(encrypted_data: &[u8], key: &SymmetricKey, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::decrypt_data_padded(encrypted_data, key.as_bytes(), cipher)
+}
+
+pub fn encrypt_data_aead(data: &[u8], key: &SymmetricKey, associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::encrypt_data_aead(data, key.as_bytes(), associated_data, cipher)
+}
+
+pub fn decrypt_data_aead(encrypted_data: &[u8], key: &SymmetricKey, associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::decrypt_data_aead(encrypted_data, key.as_bytes(), associated_data, cipher)
+}
+
+pub fn encrypt_data_deterministic(data: &[u8], key: &SymmetricKey, associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::encrypt_data_deterministic(data, key.as_bytes(), associated_data, cipher)
+}
+
+pub fn decrypt_data_deterministic(encrypted_data: &[u8], key: &SymmetricKey, associated_data: Option<&[u8]>, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::decrypt_data_deterministic(encrypted_data, key.as_bytes(), associated_data, cipher)
+}
+
+pub fn encrypt_data_convergent(data: &[u8], convergence_secret: &SymmetricKey, cipher: SymmetricCipher) -> Result<(Vec<u8>, SymmetricKey), DawnCryptoError> {
+	let (ciphertext, key) = crate::encrypt_data_convergent(data, convergence_secret.as_bytes(), cipher)?;
+	Ok((ciphertext, SymmetricKey::new(key)))
+}
+
+pub fn decrypt_data_convergent(encrypted_data: &[u8], key: &SymmetricKey, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::decrypt_data_convergent(encrypted_data, key.as_bytes(), cipher)
+}
+
+pub fn password_key_gen(password: &[u8], params: &Argon2Params) -> Result<(SymmetricKey, Vec<u8>), DawnCryptoError> {
+	let (key, encoded_params) = crate::password_key_gen(password, params)?;
+	Ok((SymmetricKey::new(key), encoded_params))
+}
+
+pub fn get_password_key(password: &[u8], encoded_params: &[u8]) -> Result<SymmetricKey, DawnCryptoError> {
+	Ok(SymmetricKey::new(crate::get_password_key(password, encoded_params)?))
+}
+
+pub fn seal_record(record_type: RecordType, record_id: &[u8], data: &[u8], storage_key: &SymmetricKey, cipher: SymmetricCipher) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::seal_record(record_type, record_id, data, storage_key.as_bytes(), cipher)
+}
+
+pub fn open_record(record_type: RecordType, record_id: &[u8], sealed: &[u8], storage_key: &SymmetricKey) -> Result<SecretBytes, DawnCryptoError> {
+	crate::open_record(record_type, record_id, sealed, storage_key.as_bytes())
+}
+
+pub fn sign_identity(sec_key: &SigningKey, identity: &[u8]) -> Result<Vec<u8>, DawnCryptoError> {
+	crate::sign_identity(sec_key.as_bytes(), identity)
+}
+
+pub fn verify_identity(signature: &[u8], pub_key: &VerifyingKey, identity: &[u8]) -> Result<(), DawnCryptoError> {
+	crate::verify_identity(signature, pub_key.as_bytes(), identity)
+}
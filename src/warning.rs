@@ -18,3 +18,6 @@
 
 pub const NONE: u8 = 0;
 pub const NO_SIGNATURE: u8 = 1;
+
+// the data was decrypted from the unauthenticated format of earlier versions and should be re-encrypted
+pub const LEGACY_FORMAT: u8 = 2;
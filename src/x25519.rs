@@ -18,20 +18,32 @@
 
 use x25519_dalek::{StaticSecret, PublicKey};
 use rand::rngs::OsRng;
+use zeroize::Zeroize;
+use crate::secret::SecretBytes;
 
-pub fn keygen() -> (Vec<u8>, Vec<u8>) {
+pub fn keygen() -> (Vec<u8>, SecretBytes) {
 	let secret = StaticSecret::random_from_rng(OsRng);
 	let public_key = PublicKey::from(&secret);
-	let secret = secret.to_bytes().to_vec();
+	let mut secret_byte_array = secret.to_bytes();
+	let secret = SecretBytes::from_slice(&secret_byte_array);
+	secret_byte_array.zeroize();
 	let public_key = public_key.as_bytes().to_vec();
 	(public_key, secret)
 }
 
-pub fn get_shared_secret(secret: &[u8], public_key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-	let secret_byte_array : [u8;32] = secret.try_into()?;
+pub fn get_shared_secret(secret: &[u8], public_key: &[u8]) -> Result<SecretBytes, Box<dyn std::error::Error + Send + Sync>> {
+	let mut secret_byte_array : [u8;32] = secret.try_into()?;
 	let secret = StaticSecret::from(secret_byte_array);
+	secret_byte_array.zeroize();
 	let pubkey_byte_array : [u8;32] = public_key.try_into()?;
 	let pk = PublicKey::from(pubkey_byte_array);
-	let shared_secret = secret.diffie_hellman(&pk).as_bytes().to_vec();
+	let shared_secret = SecretBytes::from_slice(secret.diffie_hellman(&pk).as_bytes());
 	Ok(shared_secret)
 }
+
+pub fn public_key_from_secret(secret: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+	let mut secret_byte_array : [u8;32] = secret.try_into()?;
+	let secret = StaticSecret::from(secret_byte_array);
+	secret_byte_array.zeroize();
+	Ok(PublicKey::from(&secret).as_bytes().to_vec())
+}